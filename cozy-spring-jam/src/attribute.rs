@@ -1,25 +1,71 @@
-use std::{collections::HashMap, hash::Hash};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
 
 use uuid::Uuid;
 
+/// A change to the value of an attribute.
+///
+/// All operations on an attribute are applied in the order of the variants below, whatever
+/// order their effects were applied in:
+///
+/// 1. `Set` replaces the base value. If there are several, the highest one wins.
+/// 2. `AddPercentOfBase` adds a fraction of the base value from the previous step, so two
+///    `+50%` effects add up to `+100%` rather than compounding.
+/// 3. `Add` adds a flat amount.
+/// 4. `Multiply` multiplies the value, compounding with other multipliers.
+/// 5. `ClampMin` and then `ClampMax` keep the value within bounds, so a maximum wins over a
+///    minimum above it.
+/// 6. `FinalMultiply` multiplies the clamped value, so it can take the result out of bounds.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Operation {
+    Set(f32),
+    /// The fraction of the base value to add, `0.3` for 30%.
+    AddPercentOfBase(f32),
     Add(f32),
     Multiply(f32),
+    ClampMin(f32),
+    ClampMax(f32),
+    FinalMultiply(f32),
 }
 
 impl Operation {
-    fn apply(&self, val: &mut f32) {
+    fn apply(&self, val: &mut f32, base: &mut f32) {
         match self {
+            Self::Set(x) => {
+                *base = *x;
+                *val = *x;
+            }
+            Self::AddPercentOfBase(x) => *val += x * *base,
             Self::Add(x) => *val += x,
-            Self::Multiply(x) => *val *= x,
+            Self::Multiply(x) | Self::FinalMultiply(x) => *val *= x,
+            Self::ClampMin(x) => *val = val.max(*x),
+            Self::ClampMax(x) => *val = val.min(*x),
         }
     }
 
     fn order(&self) -> usize {
         match self {
-            Self::Add(_) => 0,
-            Self::Multiply(_) => 1,
+            Self::Set(_) => 0,
+            Self::AddPercentOfBase(_) => 1,
+            Self::Add(_) => 2,
+            Self::Multiply(_) => 3,
+            Self::ClampMin(_) => 4,
+            Self::ClampMax(_) => 5,
+            Self::FinalMultiply(_) => 6,
+        }
+    }
+
+    fn value(&self) -> f32 {
+        match self {
+            Self::Set(x)
+            | Self::AddPercentOfBase(x)
+            | Self::Add(x)
+            | Self::Multiply(x)
+            | Self::ClampMin(x)
+            | Self::ClampMax(x)
+            | Self::FinalMultiply(x) => *x,
         }
     }
 }
@@ -30,10 +76,50 @@ struct Modifier<A> {
     operation: Operation,
 }
 
+/// How long an effect lasts once applied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Duration {
+    Seconds(f32),
+    /// Number of calls to [`Attributes::tick`].
+    Ticks(u32),
+}
+
+impl Duration {
+    /// Advances the duration by one tick of `delta` seconds and returns whether it ran out.
+    fn advance(&mut self, delta: f32) -> bool {
+        match self {
+            Self::Seconds(seconds) => {
+                *seconds -= delta;
+                *seconds <= 0.0
+            }
+            Self::Ticks(ticks) => {
+                *ticks = ticks.saturating_sub(1);
+                *ticks == 0
+            }
+        }
+    }
+}
+
+/// What happens when an effect is applied while effects with the same tag are active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stacking {
+    /// The new effect replaces the old ones, which restarts its duration.
+    Refresh,
+    /// Effects apply on top of each other, up to `max` at once. Further ones are ignored.
+    Stack { max: usize },
+    /// Only the strongest effect stays: the one that changes the values of the attributes it
+    /// modifies the most, compared to having no effect of that kind.
+    KeepStrongest,
+    /// The new effect is ignored.
+    Ignore,
+}
+
 #[derive(Debug, Clone)]
 pub struct Effect<A> {
     id: Uuid,
     modifiers: Vec<Modifier<A>>,
+    duration: Option<Duration>,
+    tag: Option<(&'static str, Stacking)>,
 }
 
 impl<A> Effect<A> {
@@ -41,6 +127,8 @@ impl<A> Effect<A> {
         Self {
             id: Uuid::new_v4(),
             modifiers: Vec::new(),
+            duration: None,
+            tag: None,
         }
     }
 
@@ -48,6 +136,21 @@ impl<A> Effect<A> {
         self.id
     }
 
+    /// Makes the effect expire after `duration`. Effects without one last until removed.
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = Some(duration);
+    }
+
+    /// Marks the effect as being of the kind `tag`, which decides how it stacks with other
+    /// effects of that kind. Untagged effects always stack.
+    pub fn set_tag(&mut self, tag: &'static str, stacking: Stacking) {
+        self.tag = Some((tag, stacking));
+    }
+
+    pub fn get_tag(&self) -> Option<&'static str> {
+        self.tag.map(|(tag, _)| tag)
+    }
+
     pub fn add_modifier(&mut self, attribute: A, operation: Operation) {
         self.modifiers.push(Modifier {
             attribute,
@@ -56,18 +159,33 @@ impl<A> Effect<A> {
     }
 }
 
+/// An attribute computed from the values of other attributes.
+#[derive(Debug, Clone)]
+struct Derived<A> {
+    inputs: Vec<A>,
+    formula: fn(&[f32]) -> f32,
+}
+
 pub struct Attributes<A> {
     base: HashMap<A, f32>,
+    derived: HashMap<A, Derived<A>>,
+    /// The derived attributes that use each attribute as an input.
+    dependents: HashMap<A, Vec<A>>,
     temporary_effects: HashMap<Uuid, Effect<A>>,
     cache: HashMap<A, f32>,
+    /// The last value reported for every subscribed attribute.
+    subscriptions: HashMap<A, f32>,
 }
 
 impl<A> Attributes<A> {
     pub fn new() -> Self {
         Self {
             base: HashMap::new(),
+            derived: HashMap::new(),
+            dependents: HashMap::new(),
             temporary_effects: HashMap::new(),
             cache: HashMap::new(),
+            subscriptions: HashMap::new(),
         }
     }
 }
@@ -81,6 +199,39 @@ impl<A> Default for Attributes<A> {
 impl<A: Copy + Eq + Hash> Attributes<A> {
     pub fn set_base(&mut self, attr: A, value: f32) -> &mut Self {
         self.base.insert(attr, value);
+        self.invalidate(attr);
+        self
+    }
+
+    /// Makes `attr` the result of `formula`, which gets the values of `inputs` in the same order.
+    /// Effects on `attr` apply on top of the result as if it was the base value.
+    ///
+    /// # Panics
+    ///
+    /// If `attr` would end up depending on itself.
+    pub fn derive(&mut self, attr: A, inputs: &[A], formula: fn(&[f32]) -> f32) -> &mut Self {
+        assert!(
+            inputs.iter().all(|input| !self.depends_on(*input, attr)),
+            "a derived attribute can't depend on itself"
+        );
+        if let Some(old) = self.derived.remove(&attr) {
+            for input in old.inputs {
+                if let Some(dependents) = self.dependents.get_mut(&input) {
+                    dependents.retain(|dependent| *dependent != attr);
+                }
+            }
+        }
+        for input in inputs {
+            self.dependents.entry(*input).or_default().push(attr);
+        }
+        self.derived.insert(
+            attr,
+            Derived {
+                inputs: inputs.to_vec(),
+                formula,
+            },
+        );
+        self.invalidate(attr);
         self
     }
 
@@ -88,7 +239,48 @@ impl<A: Copy + Eq + Hash> Attributes<A> {
         self.base.get(&attr).copied().unwrap_or_default()
     }
 
+    /// Applies `effect`, following its stacking rules if it has a tag.
     pub fn apply_effect(&mut self, effect: Effect<A>) -> &mut Self {
+        if let Some((tag, stacking)) = effect.tag {
+            let same_tag: Vec<Uuid> = self
+                .temporary_effects
+                .values()
+                .filter(|active| active.get_tag() == Some(tag))
+                .map(Effect::get_id)
+                .collect();
+            let keep_active = match stacking {
+                Stacking::Refresh => false,
+                Stacking::Stack { max } => {
+                    if same_tag.len() >= max {
+                        return self;
+                    }
+                    true
+                }
+                Stacking::KeepStrongest => {
+                    let impact = self.impact(&effect, tag);
+                    let stronger_active = same_tag.iter().any(|id| {
+                        let active = self.temporary_effects[id].clone();
+                        self.impact(&active, tag) >= impact
+                    });
+                    if stronger_active {
+                        return self;
+                    }
+                    false
+                }
+                Stacking::Ignore => {
+                    if !same_tag.is_empty() {
+                        return self;
+                    }
+                    true
+                }
+            };
+            if !keep_active {
+                for id in same_tag {
+                    self.remove_effect(id);
+                }
+            }
+        }
+
         self.clear_cache_for_effect(&effect);
         self.temporary_effects.insert(effect.get_id(), effect);
         self
@@ -101,31 +293,129 @@ impl<A: Copy + Eq + Hash> Attributes<A> {
         self
     }
 
+    /// Advances the durations of all effects by `delta` seconds and removes the ones that ran
+    /// out, returning them so callers can react to their expiry.
+    pub fn tick(&mut self, delta: f32) -> Vec<Effect<A>> {
+        let expired_ids: Vec<Uuid> = self
+            .temporary_effects
+            .values_mut()
+            .filter_map(|effect| {
+                let duration = effect.duration.as_mut()?;
+                duration.advance(delta).then_some(effect.id)
+            })
+            .collect();
+
+        let mut expired = Vec::new();
+        for id in expired_ids {
+            if let Some(effect) = self.temporary_effects.remove(&id) {
+                self.clear_cache_for_effect(&effect);
+                expired.push(effect);
+            }
+        }
+        expired
+    }
+
+    /// Advances the effects like [`Attributes::tick`] and returns the tags of the ones that ran
+    /// out, with an empty tag for untagged effects.
+    pub fn tick_tags(&mut self, delta: f32) -> Vec<&'static str> {
+        self.tick(delta)
+            .iter()
+            .map(|effect| effect.get_tag().unwrap_or_default())
+            .collect()
+    }
+
     pub fn get(&mut self, attr: A) -> f32 {
         if let Some(cached) = self.cache.get(&attr) {
             return *cached;
         }
+        let value = self.evaluate(attr, None, None);
+        self.cache.insert(attr, value);
+        value
+    }
 
-        let mut value = self.get_base(attr);
-
-        let mut operations: Vec<Operation> = Vec::new();
-        for effect in self.temporary_effects.values() {
-            for modifier in &effect.modifiers {
-                if modifier.attribute == attr {
-                    operations.push(modifier.operation.clone());
-                }
+    /// Computes the value of `attr` from scratch, leaving out the active effects tagged
+    /// `skip_tag` and applying `extra` as if it was active.
+    fn evaluate(
+        &mut self,
+        attr: A,
+        skip_tag: Option<&'static str>,
+        extra: Option<&Effect<A>>,
+    ) -> f32 {
+        let mut base = match self.derived.get(&attr).cloned() {
+            Some(derived) => {
+                let inputs: Vec<f32> = derived
+                    .inputs
+                    .iter()
+                    .map(|input| self.get(*input))
+                    .collect();
+                (derived.formula)(&inputs)
             }
-        }
-        operations.sort_by_key(Operation::order);
+            None => self.get_base(attr),
+        };
+        let mut value = base;
+
+        let mut operations: Vec<Operation> = self
+            .temporary_effects
+            .values()
+            .filter(|effect| skip_tag.is_none() || effect.get_tag() != skip_tag)
+            .chain(extra)
+            .flat_map(|effect| &effect.modifiers)
+            .filter(|modifier| modifier.attribute == attr)
+            .map(|modifier| modifier.operation.clone())
+            .collect();
+        // Sorting by value within a stage makes the highest `Set` the last one applied
+        operations.sort_by(|a, b| {
+            a.order()
+                .cmp(&b.order())
+                .then(a.value().total_cmp(&b.value()))
+        });
 
         for operation in operations {
-            operation.apply(&mut value);
+            operation.apply(&mut value, &mut base);
         }
-
-        self.cache.insert(attr, value);
         value
     }
 
+    /// How much `effect` changes the values of the attributes it modifies, summed over them,
+    /// compared to having no active effect tagged `tag`.
+    fn impact(&mut self, effect: &Effect<A>, tag: &'static str) -> f32 {
+        let attrs: HashSet<A> = effect
+            .modifiers
+            .iter()
+            .map(|modifier| modifier.attribute)
+            .collect();
+        attrs
+            .into_iter()
+            .map(|attr| {
+                let with = self.evaluate(attr, Some(tag), Some(effect));
+                let without = self.evaluate(attr, Some(tag), None);
+                (with - without).abs()
+            })
+            .sum()
+    }
+
+    /// Starts tracking changes to the value of `attr`, which are reported by
+    /// [`Attributes::take_changes`].
+    pub fn subscribe(&mut self, attr: A) -> &mut Self {
+        let value = self.get(attr);
+        self.subscriptions.insert(attr, value);
+        self
+    }
+
+    /// Returns the subscribed attributes whose value changed since the last call, with their new
+    /// value. Changes that were undone in the meantime are not reported.
+    pub fn take_changes(&mut self) -> Vec<(A, f32)> {
+        let subscribed: Vec<A> = self.subscriptions.keys().copied().collect();
+        let mut changes = Vec::new();
+        for attr in subscribed {
+            let value = self.get(attr);
+            if self.subscriptions.insert(attr, value) != Some(value) {
+                changes.push((attr, value));
+            }
+        }
+        changes
+    }
+
     pub fn get_int(&mut self, attr: A) -> i32 {
         self.get(attr).round() as i32
     }
@@ -136,7 +426,318 @@ impl<A: Copy + Eq + Hash> Attributes<A> {
 
     fn clear_cache_for_effect(&mut self, effect: &Effect<A>) {
         for modifier in &effect.modifiers {
-            self.cache.remove(&modifier.attribute);
+            self.invalidate(modifier.attribute);
+        }
+    }
+
+    /// Clears the cached value of `attr` and of every attribute derived from it.
+    fn invalidate(&mut self, attr: A) {
+        let mut pending = vec![attr];
+        while let Some(attr) = pending.pop() {
+            self.cache.remove(&attr);
+            if let Some(dependents) = self.dependents.get(&attr) {
+                pending.extend(dependents.iter().copied());
+            }
         }
     }
+
+    /// Whether `attr` is `other` or derived from it, directly or through other attributes.
+    fn depends_on(&self, attr: A, other: A) -> bool {
+        attr == other
+            || self.derived.get(&attr).is_some_and(|derived| {
+                derived
+                    .inputs
+                    .iter()
+                    .any(|input| self.depends_on(*input, other))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Attr {
+        Speed,
+        DashDuration,
+        DashDistance,
+        DashCooldown,
+    }
+
+    fn timed_boost(duration: Duration) -> Effect<Attr> {
+        let mut effect = Effect::new();
+        effect.add_modifier(Attr::Speed, Operation::Add(5.0));
+        effect.set_duration(duration);
+        effect
+    }
+
+    #[test]
+    fn seconds_expire_once_elapsed() {
+        let mut attributes = Attributes::new();
+        attributes.set_base(Attr::Speed, 10.0);
+        let effect = timed_boost(Duration::Seconds(1.0));
+        let id = effect.get_id();
+        attributes.apply_effect(effect);
+
+        assert!(attributes.tick(0.6).is_empty());
+        assert_eq!(attributes.get(Attr::Speed), 15.0);
+        let expired = attributes.tick(0.6);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].get_id(), id);
+        assert_eq!(attributes.get(Attr::Speed), 10.0);
+    }
+
+    #[test]
+    fn ticks_expire_after_that_many_calls() {
+        let mut attributes = Attributes::new();
+        attributes.set_base(Attr::Speed, 10.0);
+        attributes.apply_effect(timed_boost(Duration::Ticks(3)));
+
+        assert!(attributes.tick(100.0).is_empty());
+        assert!(attributes.tick(0.0).is_empty());
+        assert_eq!(attributes.get(Attr::Speed), 15.0);
+        assert_eq!(attributes.tick(0.0).len(), 1);
+        assert_eq!(attributes.get(Attr::Speed), 10.0);
+    }
+
+    #[test]
+    fn tick_tags_names_expired_effects() {
+        let mut attributes = Attributes::new();
+        let mut tagged = timed_boost(Duration::Ticks(1));
+        tagged.set_tag("boost", Stacking::Refresh);
+        attributes
+            .apply_effect(tagged)
+            .apply_effect(timed_boost(Duration::Ticks(1)))
+            .apply_effect(timed_boost(Duration::Ticks(2)));
+
+        let mut tags = attributes.tick_tags(0.0);
+        tags.sort();
+        assert_eq!(tags, ["", "boost"]);
+        assert_eq!(attributes.tick_tags(0.0), [""]);
+    }
+
+    #[test]
+    fn effects_without_duration_never_expire() {
+        let mut attributes = Attributes::new();
+        attributes.set_base(Attr::Speed, 10.0);
+        let mut effect = Effect::new();
+        effect.add_modifier(Attr::Speed, Operation::Add(5.0));
+        attributes.apply_effect(effect);
+
+        for _ in 0..10 {
+            assert!(attributes.tick(1.0).is_empty());
+        }
+        assert_eq!(attributes.get(Attr::Speed), 15.0);
+    }
+
+    fn tagged(operation: Operation, stacking: Stacking) -> Effect<Attr> {
+        let mut effect = Effect::new();
+        effect.add_modifier(Attr::Speed, operation);
+        effect.set_tag("boost", stacking);
+        effect
+    }
+
+    #[test]
+    fn refresh_replaces_the_active_effect() {
+        let mut attributes = Attributes::new();
+        attributes.set_base(Attr::Speed, 10.0);
+        let mut first = tagged(Operation::Add(5.0), Stacking::Refresh);
+        first.set_duration(Duration::Ticks(2));
+        attributes.apply_effect(first);
+        attributes.tick(0.0);
+        let mut second = tagged(Operation::Add(3.0), Stacking::Refresh);
+        second.set_duration(Duration::Ticks(2));
+        attributes.apply_effect(second);
+
+        assert_eq!(attributes.get(Attr::Speed), 13.0);
+        assert!(attributes.tick(0.0).is_empty());
+        assert_eq!(attributes.tick(0.0).len(), 1);
+    }
+
+    #[test]
+    fn stack_applies_effects_up_to_the_limit() {
+        let mut attributes = Attributes::new();
+        attributes.set_base(Attr::Speed, 10.0);
+        for _ in 0..5 {
+            attributes.apply_effect(tagged(Operation::Add(1.0), Stacking::Stack { max: 3 }));
+        }
+        assert_eq!(attributes.get(Attr::Speed), 13.0);
+    }
+
+    #[test]
+    fn keep_strongest_compares_the_change_in_value() {
+        let mut attributes = Attributes::new();
+        attributes.set_base(Attr::Speed, 100.0);
+        // +20% of 100 beats a flat +5, even though 0.2 is the smaller number
+        attributes.apply_effect(tagged(
+            Operation::AddPercentOfBase(0.2),
+            Stacking::KeepStrongest,
+        ));
+        attributes.apply_effect(tagged(Operation::Add(5.0), Stacking::KeepStrongest));
+        assert_eq!(attributes.get(Attr::Speed), 120.0);
+
+        attributes.apply_effect(tagged(Operation::Multiply(1.5), Stacking::KeepStrongest));
+        assert_eq!(attributes.get(Attr::Speed), 150.0);
+    }
+
+    #[test]
+    fn keep_strongest_ignores_effects_of_other_kinds() {
+        let mut attributes = Attributes::new();
+        attributes.set_base(Attr::Speed, 100.0);
+        let mut untagged = Effect::new();
+        untagged.add_modifier(Attr::Speed, Operation::Multiply(2.0));
+        attributes.apply_effect(untagged);
+        attributes.apply_effect(tagged(Operation::Add(10.0), Stacking::KeepStrongest));
+        attributes.apply_effect(tagged(Operation::Add(20.0), Stacking::KeepStrongest));
+        assert_eq!(attributes.get(Attr::Speed), 240.0);
+    }
+
+    #[test]
+    fn ignore_keeps_the_first_effect() {
+        let mut attributes = Attributes::new();
+        attributes.set_base(Attr::Speed, 10.0);
+        attributes.apply_effect(tagged(Operation::Add(1.0), Stacking::Ignore));
+        attributes.apply_effect(tagged(Operation::Add(5.0), Stacking::Ignore));
+        assert_eq!(attributes.get(Attr::Speed), 11.0);
+    }
+
+    fn with_operations(base: f32, operations: &[Operation]) -> f32 {
+        let mut attributes = Attributes::new();
+        attributes.set_base(Attr::Speed, base);
+        // Applied in reverse to show that the order of the effects doesn't matter
+        for operation in operations.iter().rev() {
+            let mut effect = Effect::new();
+            effect.add_modifier(Attr::Speed, operation.clone());
+            attributes.apply_effect(effect);
+        }
+        attributes.get(Attr::Speed)
+    }
+
+    #[test]
+    fn highest_set_replaces_the_base_value() {
+        let operations = [
+            Operation::Set(3.0),
+            Operation::Set(5.0),
+            Operation::Add(1.0),
+        ];
+        assert_eq!(with_operations(10.0, &operations), 6.0);
+    }
+
+    #[test]
+    fn percent_of_base_adds_up_from_the_set_value() {
+        let operations = [
+            Operation::Set(100.0),
+            Operation::AddPercentOfBase(0.5),
+            Operation::AddPercentOfBase(0.5),
+            Operation::Add(10.0),
+        ];
+        assert_eq!(with_operations(10.0, &operations), 210.0);
+    }
+
+    #[test]
+    fn add_comes_before_multiply() {
+        let operations = [Operation::Multiply(2.0), Operation::Add(5.0)];
+        assert_eq!(with_operations(10.0, &operations), 30.0);
+    }
+
+    #[test]
+    fn multipliers_compound() {
+        let operations = [Operation::Multiply(2.0), Operation::Multiply(3.0)];
+        assert_eq!(with_operations(1.0, &operations), 6.0);
+    }
+
+    #[test]
+    fn clamps_come_after_multiply_and_max_wins() {
+        let operations = [Operation::ClampMin(50.0), Operation::Multiply(0.5)];
+        assert_eq!(with_operations(10.0, &operations), 50.0);
+        let operations = [Operation::ClampMin(50.0), Operation::ClampMax(20.0)];
+        assert_eq!(with_operations(10.0, &operations), 20.0);
+    }
+
+    #[test]
+    fn final_multiply_applies_to_the_clamped_value() {
+        let operations = [Operation::ClampMin(100.0), Operation::FinalMultiply(0.5)];
+        assert_eq!(with_operations(10.0, &operations), 50.0);
+        let operations = [Operation::ClampMax(100.0), Operation::FinalMultiply(2.0)];
+        assert_eq!(with_operations(500.0, &operations), 200.0);
+    }
+
+    fn product(inputs: &[f32]) -> f32 {
+        inputs.iter().product()
+    }
+
+    fn with_dash_distance() -> Attributes<Attr> {
+        let mut attributes = Attributes::new();
+        attributes
+            .set_base(Attr::Speed, 100.0)
+            .set_base(Attr::DashDuration, 0.5)
+            .derive(
+                Attr::DashDistance,
+                &[Attr::Speed, Attr::DashDuration],
+                product,
+            );
+        attributes
+    }
+
+    #[test]
+    fn derived_value_follows_input_base_values() {
+        let mut attributes = with_dash_distance();
+        assert_eq!(attributes.get(Attr::DashDistance), 50.0);
+        attributes.set_base(Attr::Speed, 200.0);
+        assert_eq!(attributes.get(Attr::DashDistance), 100.0);
+    }
+
+    #[test]
+    fn derived_value_follows_effects_on_inputs() {
+        let mut attributes = with_dash_distance();
+        assert_eq!(attributes.get(Attr::DashDistance), 50.0);
+
+        let mut effect = Effect::new();
+        effect.add_modifier(Attr::Speed, Operation::Multiply(2.0));
+        effect.set_duration(Duration::Ticks(1));
+        attributes.apply_effect(effect);
+        assert_eq!(attributes.get(Attr::DashDistance), 100.0);
+
+        assert_eq!(attributes.tick(0.0).len(), 1);
+        assert_eq!(attributes.get(Attr::DashDistance), 50.0);
+    }
+
+    #[test]
+    fn effects_on_derived_values_apply_on_top_of_the_formula() {
+        let mut attributes = with_dash_distance();
+        let mut effect = Effect::new();
+        effect.add_modifier(Attr::DashDistance, Operation::Add(10.0));
+        attributes.apply_effect(effect);
+        assert_eq!(attributes.get(Attr::DashDistance), 60.0);
+
+        attributes.set_base(Attr::Speed, 200.0);
+        assert_eq!(attributes.get(Attr::DashDistance), 110.0);
+    }
+
+    #[test]
+    fn chained_derived_values_are_invalidated() {
+        let mut attributes = with_dash_distance();
+        attributes.derive(Attr::DashCooldown, &[Attr::DashDistance], |inputs| {
+            inputs[0] / 100.0
+        });
+        assert_eq!(attributes.get(Attr::DashCooldown), 0.5);
+
+        attributes.set_base(Attr::DashDuration, 1.0);
+        assert_eq!(attributes.get(Attr::DashCooldown), 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "can't depend on itself")]
+    fn self_referencing_derive_panics() {
+        let mut attributes: Attributes<Attr> = Attributes::new();
+        attributes.derive(Attr::Speed, &[Attr::Speed], product);
+    }
+
+    #[test]
+    #[should_panic(expected = "can't depend on itself")]
+    fn cyclic_derive_panics() {
+        let mut attributes = with_dash_distance();
+        attributes.derive(Attr::Speed, &[Attr::DashDistance], product);
+    }
 }
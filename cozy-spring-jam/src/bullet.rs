@@ -1,8 +1,11 @@
 use std::collections::HashMap;
 
 use crate::{
-    attribute::{Attributes, Effect, Operation},
+    attribute::{Attributes, Effect, Operation, Stacking},
     enemy::Enemy,
+    hazard::HazardTracker,
+    room::Room,
+    world::World,
 };
 use godot::{
     classes::{
@@ -34,6 +37,20 @@ impl BulletAttribute {
     ];
 }
 
+/// The speed and power lost by bouncing off a wall, which adds up over at most `max_bounces`
+/// bounces.
+fn bounce_effect(
+    speed_factor: f32,
+    power_factor: f32,
+    max_bounces: usize,
+) -> Effect<BulletAttribute> {
+    let mut effect = Effect::new();
+    effect.add_modifier(BulletAttribute::Speed, Operation::Multiply(speed_factor));
+    effect.add_modifier(BulletAttribute::Power, Operation::Multiply(power_factor));
+    effect.set_tag("bounce", Stacking::Stack { max: max_bounces });
+    effect
+}
+
 #[derive(GodotClass)]
 #[class(base=RigidBody2D)]
 struct Bullet {
@@ -51,6 +68,8 @@ struct Bullet {
 
     attributes: Attributes<BulletAttribute>,
 
+    hazards: HazardTracker<BulletAttribute>,
+
     dead: bool,
 
     is_player_bullet: bool,
@@ -69,10 +88,14 @@ impl IRigidBody2D for Bullet {
             .set_base(BulletAttribute::MaxBounces, 0.0)
             .set_base(BulletAttribute::BouncePowerPreservation, 1.0)
             .set_base(BulletAttribute::BounceSpeedPreservation, 1.0);
+        for attr in BulletAttribute::ALL {
+            attributes.subscribe(*attr);
+        }
         Self {
             bounce_sfx: None,
             animated_sprite: None,
             attributes,
+            hazards: HazardTracker::new(BulletAttribute::Speed),
             age: 0.0,
             bounces: 0,
             dead: false,
@@ -102,10 +125,13 @@ impl IRigidBody2D for Bullet {
     }
 
     fn physics_process(&mut self, delta: f64) {
+        self.tick_effects(delta);
+        self.emit_attribute_changes();
         self.age += delta as f32;
         if self.age > self.attr().get(BulletAttribute::Lifetime) {
             self.decay();
         }
+        self.update_hazards(delta);
 
         let norm_vel = self.base().get_linear_velocity().normalized_or_zero();
         let new_vel = self.attr().get(BulletAttribute::Speed) * norm_vel;
@@ -120,6 +146,14 @@ impl Bullet {
 
     #[signal]
     fn decayed(pos: Vector2);
+
+    /// Emitted when the value of a bullet attribute changes, with the name of the attribute.
+    #[signal]
+    fn attribute_changed(attribute: GString, value: f32);
+
+    /// Emitted with the tag of a timed bullet effect that ran out.
+    #[signal]
+    fn effect_expired(tag: GString);
 }
 
 impl Bullet {
@@ -130,6 +164,7 @@ impl Bullet {
     fn on_body_entered(&mut self, node: Gd<Node>) {
         let mut should_explode = false;
         if node.is_class("TileMapLayer") {
+            self.damage_wall(&node);
             self.bounces += 1;
             if self.bounces > self.attr().get_uint(BulletAttribute::MaxBounces) {
                 should_explode = true;
@@ -147,17 +182,49 @@ impl Bullet {
         }
     }
 
+    fn tick_effects(&mut self, delta: f64) {
+        for tag in self.attributes.tick_tags(delta as f32) {
+            self.signals().effect_expired().emit(&GString::from(tag));
+        }
+    }
+
+    fn emit_attribute_changes(&mut self) {
+        for (attr, value) in self.attributes.take_changes() {
+            self.signals()
+                .attribute_changed()
+                .emit(&GString::from(format!("{attr:?}")), value);
+        }
+    }
+
+    /// Slows the bullet down while it flies over water or mud.
+    fn update_hazards(&mut self, delta: f64) {
+        let Some(world) = World::for_node(self.base().upcast_ref()) else {
+            return;
+        };
+        let tile = world.bind().floor_at(self.base().get_global_position());
+        self.hazards
+            .update(tile, delta as f32, &mut self.attributes);
+    }
+
+    /// Damages the wall the bullet hit, if the tile map belongs to a room.
+    fn damage_wall(&mut self, node: &Gd<Node>) {
+        let Some(Ok(mut room)) = node.get_parent().map(|parent| parent.try_cast::<Room>()) else {
+            return;
+        };
+        let power = self.attr().get(BulletAttribute::Power);
+        let pos = self.base().get_global_position();
+        room.bind_mut().damage_wall(pos, power);
+    }
+
     fn bounce(&mut self) {
         self.play_bounce();
 
         let speed_factor = self.attr().get(BulletAttribute::BounceSpeedPreservation);
         let power_factor = self.attr().get(BulletAttribute::BouncePowerPreservation);
+        let max_bounces = self.attr().get_uint(BulletAttribute::MaxBounces) as usize;
 
-        let mut bounce_effect = Effect::new();
-        bounce_effect.add_modifier(BulletAttribute::Speed, Operation::Multiply(speed_factor));
-        bounce_effect.add_modifier(BulletAttribute::Power, Operation::Multiply(power_factor));
-
-        self.attr().apply_effect(bounce_effect);
+        let effect = bounce_effect(speed_factor, power_factor, max_bounces);
+        self.attr().apply_effect(effect);
     }
 
     fn free_if_dead(&mut self) {
@@ -331,3 +398,21 @@ impl BulletExplosion {
         self.base_mut().queue_free();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounce_effect_stops_stacking_at_max_bounces() {
+        let mut attributes = Attributes::new();
+        attributes
+            .set_base(BulletAttribute::Speed, 100.0)
+            .set_base(BulletAttribute::Power, 8.0);
+        for _ in 0..5 {
+            attributes.apply_effect(bounce_effect(0.5, 0.5, 2));
+        }
+        assert_eq!(attributes.get(BulletAttribute::Speed), 25.0);
+        assert_eq!(attributes.get(BulletAttribute::Power), 2.0);
+    }
+}
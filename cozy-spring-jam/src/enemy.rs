@@ -2,10 +2,12 @@ use crate::bullet::BulletAttribute;
 use crate::enemy_drop::EnemyDrop;
 use crate::gun::{Gun, GunAttribute};
 use crate::player::Player;
+use crate::utils::mix_seed;
+use crate::world::{World, seeded_rng};
 use godot::builtin::{Array, Vector2, real};
 use godot::classes::{
     Area2D, CharacterBody2D, IArea2D, ICharacterBody2D, IRigidBody2D, NavigationAgent2D, Node,
-    Node2D, PackedScene, RandomNumberGenerator, RigidBody2D, Timer,
+    Node2D, PackedScene, RigidBody2D, Timer,
 };
 use godot::global::{godot_print, randi_range};
 use godot::obj::{Base, Gd, NewGd, WithBaseField, WithUserSignals};
@@ -29,6 +31,8 @@ pub struct Enemy {
     #[export]
     loot_chances: Array<u16>,
 
+    gun_seed: Option<u64>,
+
     base: Base<CharacterBody2D>,
 }
 
@@ -112,9 +116,13 @@ impl Enemy {
                 }
             }
             self.base_mut().queue_free();
+            self.signals().died().emit();
         }
     }
 
+    #[signal]
+    pub fn died();
+
     const GUN_DISTANCE: f32 = 24.0;
     #[func]
     fn position_gun(&self, target_position: Vector2) {
@@ -129,9 +137,23 @@ impl Enemy {
         }
     }
 
+    /// Whether the walls of the room the enemy is in leave a clear view of `target`.
+    #[func]
+    fn can_see(&self, target: Vector2) -> bool {
+        let Some(world) = World::for_node(self.base().upcast_ref()) else {
+            return true;
+        };
+        let pos = self.base().get_global_position();
+        let room = world.bind().room_containing(pos);
+        room.is_none_or(|(_, room)| room.bind().has_line_of_sight(pos, target))
+    }
+
     #[func]
     fn shoot(&mut self, target: Vector2) -> bool {
         self.position_gun(target);
+        if !self.can_see(target) {
+            return false;
+        }
         if let Some(mut gun) = self.get_gun() {
             if gun.bind().get_on_cooldown() {
                 return false;
@@ -141,12 +163,30 @@ impl Enemy {
         true
     }
 
-    fn randomize_gun(&mut self) {
+    pub fn set_gun_seed(&mut self, seed: u64) {
+        self.gun_seed = Some(seed);
+    }
+
+    pub fn gun_seed(&self) -> Option<u64> {
+        self.gun_seed
+    }
+
+    /// Seed for enemies placed directly in a scene, derived from the run seed and their position.
+    fn placement_seed(&self) -> u64 {
+        let run_seed = World::run_seed_for(self.base().upcast_ref());
+        let pos = self.base().get_global_position();
+        mix_seed(
+            mix_seed(run_seed, pos.x.to_bits() as u64),
+            pos.y.to_bits() as u64,
+        )
+    }
+
+    fn randomize_gun(&mut self, seed: u64) {
         let Some(mut gun) = self.get_gun() else {
             return;
         };
         let mut gun = gun.bind_mut();
-        let mut rng = RandomNumberGenerator::new_gd();
+        let mut rng = seeded_rng(seed);
         gun.attr()
             .set_base(GunAttribute::Spread, rng.randf_range(0.0, 0.8))
             .set_base(GunAttribute::Cooldown, rng.randf_range(0.1, 1.0))
@@ -181,12 +221,17 @@ impl ICharacterBody2D for Enemy {
             gun: None,
             loot_pool: Default::default(),
             loot_chances: Default::default(),
+            gun_seed: None,
             base,
         }
     }
 
     fn ready(&mut self) {
-        self.randomize_gun();
+        let seed = match self.gun_seed {
+            Some(seed) => seed,
+            None => self.placement_seed(),
+        };
+        self.randomize_gun(seed);
         self.base_mut().set_y_sort_enabled(true);
     }
 }
@@ -1,27 +1,120 @@
-use crate::player::Player;
-use godot::classes::{IRigidBody2D, Node, RigidBody2D};
+use crate::attribute::{Duration, Effect, Operation, Stacking};
+use crate::player::{Player, PlayerAttribute};
+use godot::builtin::{Array, Vector2, real};
+use godot::classes::{IRigidBody2D, Input, Node, Node2D, PackedScene, RigidBody2D};
 use godot::global::godot_print;
 use godot::obj::{Base, Gd, WithBaseField, WithUserSignals};
-use godot::prelude::{GodotClass, godot_api};
+use godot::prelude::{GodotClass, ToGodot, godot_api};
 
 #[derive(GodotClass)]
 #[class(base=RigidBody2D)]
 pub struct EnemyDrop {
     #[export]
     gained_health: i16,
+
+    /// Extra speed given to the player on pickup, as a fraction of their speed.
+    #[export]
+    speed_boost: f32,
+
+    /// Seconds the speed boost lasts.
+    #[export]
+    speed_boost_duration: f32,
+
+    /// Maximum health permanently added to the player on pickup.
+    #[export]
+    max_health_bonus: f32,
+
+    /// Health the player pays for the drop. Drops with a price are only picked up through the
+    /// `interact` action.
+    #[export]
+    cost: i16,
+
+    /// Whether the player has to use the `interact` action to pick up the drop.
+    #[export]
+    needs_interaction: bool,
+
+    /// Scenes spawned around the drop when it is picked up, like the contents of a chest.
+    #[export]
+    loot: Array<Gd<PackedScene>>,
+
+    /// The player touching the drop, waiting for them to interact with it.
+    player_in_reach: Option<Gd<Player>>,
+
     base: Base<RigidBody2D>,
 }
 
 #[godot_api]
 impl EnemyDrop {
+    const LOOT_SPREAD: real = 24.0;
+
     fn on_body_entered(&mut self, node: Gd<Node>) {
+        let Ok(player_node) = node.try_cast::<Player>() else {
+            return;
+        };
+        if self.needs_interaction || self.cost > 0 {
+            self.player_in_reach = Some(player_node);
+        } else {
+            self.pick_up(player_node);
+        }
+    }
+
+    fn on_body_exited(&mut self, node: Gd<Node>) {
         if node.is_class("Player") {
-            godot_print!("Player picked up");
-            let mut player_node: Gd<Player> = node.cast();
+            self.player_in_reach = None;
+        }
+    }
+
+    fn pick_up(&mut self, mut player_node: Gd<Player>) {
+        if self.cost > 0 && !player_node.bind_mut().pay_health(self.cost) {
+            godot_print!("Player can't afford the drop");
+            return;
+        }
+        godot_print!("Player picked up");
+        if self.max_health_bonus != 0.0 {
+            let mut effect = Effect::new();
+            effect.add_modifier(
+                PlayerAttribute::MaxHealth,
+                Operation::Add(self.max_health_bonus),
+            );
+            player_node.bind_mut().attr().apply_effect(effect);
+        }
+        if self.gained_health != 0 {
             player_node
                 .bind_mut()
                 .damage_player(self.gained_health * -1);
-            self.base_mut().queue_free();
+        }
+        if self.speed_boost != 0.0 {
+            let mut effect = Effect::new();
+            effect.add_modifier(
+                PlayerAttribute::Speed,
+                Operation::Multiply(1.0 + self.speed_boost),
+            );
+            effect.set_duration(Duration::Seconds(self.speed_boost_duration));
+            effect.set_tag("speed_boost", Stacking::Refresh);
+            player_node.bind_mut().attr().apply_effect(effect);
+        }
+        self.drop_loot();
+        self.signals()
+            .picked_up()
+            .emit(&player_node.upcast::<Node>());
+        self.base_mut().queue_free();
+    }
+
+    /// Spawns the loot in a circle around the drop, next to it in the scene tree.
+    fn drop_loot(&mut self) {
+        let Some(mut parent) = self.base().get_parent() else {
+            return;
+        };
+        let position = self.base().get_position();
+        let count = self.loot.len();
+        for (i, scene) in self.loot.iter_shared().enumerate() {
+            let Some(item) = scene.instantiate() else {
+                continue;
+            };
+            let mut item: Gd<Node2D> = item.cast();
+            let angle = std::f32::consts::TAU * i as f32 / count as f32;
+            item.set_position(position + Vector2::from_angle(angle) * Self::LOOT_SPREAD);
+            parent.call_deferred("add_child", &[item.to_variant()]);
         }
     }
 
@@ -34,6 +127,13 @@ impl IRigidBody2D for EnemyDrop {
     fn init(base: Base<Self::Base>) -> Self {
         Self {
             gained_health: 0,
+            speed_boost: 0.0,
+            speed_boost_duration: 5.0,
+            max_health_bonus: 0.0,
+            cost: 0,
+            needs_interaction: false,
+            loot: Array::new(),
+            player_in_reach: None,
             base,
         }
     }
@@ -44,5 +144,17 @@ impl IRigidBody2D for EnemyDrop {
         self.signals()
             .body_entered()
             .connect_self(Self::on_body_entered);
+        self.signals()
+            .body_exited()
+            .connect_self(Self::on_body_exited);
+    }
+
+    fn process(&mut self, _delta: f64) {
+        let Some(player_node) = self.player_in_reach.clone() else {
+            return;
+        };
+        if Input::singleton().is_action_just_pressed("interact") {
+            self.pick_up(player_node);
+        }
     }
 }
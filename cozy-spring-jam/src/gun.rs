@@ -17,6 +17,8 @@ pub enum GunAttribute {
     BulletCount,
     MultishotSpread,
     Bullets(BulletAttribute),
+    /// Damage per second when firing continuously, derived from the other attributes.
+    Dps,
 }
 
 #[derive(GodotClass)]
@@ -65,7 +67,24 @@ impl INode2D for Gun {
             .set_base(
                 GunAttribute::Bullets(BulletAttribute::BounceSpeedPreservation),
                 1.0,
-            );
+            )
+            .derive(
+                GunAttribute::Dps,
+                &[
+                    GunAttribute::Cooldown,
+                    GunAttribute::BulletCount,
+                    GunAttribute::Bullets(BulletAttribute::Power),
+                ],
+                |inputs| match inputs {
+                    [cooldown, count, power] if *cooldown > 0.0 => count.round() * power / cooldown,
+                    _ => 0.0,
+                },
+            )
+            .subscribe(GunAttribute::Dps)
+            .subscribe(GunAttribute::Cooldown)
+            .subscribe(GunAttribute::Spread)
+            .subscribe(GunAttribute::BulletCount)
+            .subscribe(GunAttribute::MultishotSpread);
         Self {
             shoot_sfx: None,
             animation: None,
@@ -94,6 +113,28 @@ impl INode2D for Gun {
         }
         self.play_animation("default", false);
     }
+
+    fn process(&mut self, delta: f64) {
+        self.tick_effects(delta);
+        self.handle_attribute_changes();
+    }
+}
+
+#[godot_api]
+impl Gun {
+    /// Emitted when the value of a gun attribute changes, with the name of the attribute.
+    #[signal]
+    fn attribute_changed(attribute: GString, value: f32);
+
+    /// Emitted with the tag of a timed gun effect that ran out.
+    #[signal]
+    fn effect_expired(tag: GString);
+
+    /// Damage per second when firing continuously.
+    #[func]
+    fn dps(&mut self) -> f32 {
+        self.attr().get(GunAttribute::Dps)
+    }
 }
 
 impl Gun {
@@ -101,6 +142,25 @@ impl Gun {
         &mut self.attributes
     }
 
+    fn tick_effects(&mut self, delta: f64) {
+        for tag in self.attributes.tick_tags(delta as f32) {
+            self.signals().effect_expired().emit(&GString::from(tag));
+        }
+    }
+
+    fn handle_attribute_changes(&mut self) {
+        for (attr, value) in self.attributes.take_changes() {
+            if attr == GunAttribute::Cooldown {
+                if let Some(mut timer) = self.get_cooldown_timer() {
+                    timer.set_wait_time(value as f64);
+                }
+            }
+            self.signals()
+                .attribute_changed()
+                .emit(&GString::from(format!("{attr:?}")), value);
+        }
+    }
+
     pub fn shoot(&mut self) {
         if self.get_on_cooldown() {
             return;
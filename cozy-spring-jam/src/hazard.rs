@@ -0,0 +1,108 @@
+use crate::attribute::{Attributes, Effect, Operation};
+use crate::room::generation::FloorTile;
+use std::hash::Hash;
+use uuid::Uuid;
+
+/// Applies the effects of the floor tile under an entity to its attributes.
+///
+/// Slowing tiles multiply `speed_attribute` through a temporary effect, which is swapped whenever
+/// the entity steps onto a different tile. Damage over time is accumulated until a whole point
+/// is reached.
+pub struct HazardTracker<A> {
+    speed_attribute: A,
+    tile: FloorTile,
+    speed_effect: Option<Uuid>,
+    pending_damage: f32,
+}
+
+impl<A: Copy + Eq + Hash> HazardTracker<A> {
+    pub fn new(speed_attribute: A) -> Self {
+        Self {
+            speed_attribute,
+            tile: FloorTile::None,
+            speed_effect: None,
+            pending_damage: 0.0,
+        }
+    }
+
+    /// Updates the tile the entity stands on and returns the damage it takes this frame.
+    pub fn update(&mut self, tile: FloorTile, delta: f32, attributes: &mut Attributes<A>) -> i16 {
+        if tile != self.tile {
+            self.tile = tile;
+            if let Some(id) = self.speed_effect.take() {
+                attributes.remove_effect(id);
+            }
+            let speed_factor = tile.speed_factor();
+            if speed_factor != 1.0 {
+                let mut effect = Effect::new();
+                effect.add_modifier(self.speed_attribute, Operation::Multiply(speed_factor));
+                self.speed_effect = Some(effect.get_id());
+                attributes.apply_effect(effect);
+            }
+        }
+
+        let damage_per_second = tile.damage_per_second();
+        if damage_per_second <= 0.0 {
+            self.pending_damage = 0.0;
+            return 0;
+        }
+        self.pending_damage += damage_per_second * delta;
+        let damage = self.pending_damage.floor();
+        self.pending_damage -= damage;
+        damage as i16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Attr {
+        Speed,
+    }
+
+    fn setup() -> (HazardTracker<Attr>, Attributes<Attr>) {
+        let mut attributes = Attributes::new();
+        attributes.set_base(Attr::Speed, 100.0);
+        (HazardTracker::new(Attr::Speed), attributes)
+    }
+
+    fn assert_speed(attributes: &mut Attributes<Attr>, expected: f32) {
+        let speed = attributes.get(Attr::Speed);
+        assert!(
+            (speed - expected).abs() < 1e-3,
+            "speed is {speed}, not {expected}"
+        );
+    }
+
+    #[test]
+    fn slowing_tiles_swap_the_speed_effect() {
+        let (mut hazards, mut attributes) = setup();
+        hazards.update(FloorTile::Water, 0.1, &mut attributes);
+        assert_speed(&mut attributes, 60.0);
+        hazards.update(FloorTile::Water, 0.1, &mut attributes);
+        assert_speed(&mut attributes, 60.0);
+        hazards.update(FloorTile::Mud, 0.1, &mut attributes);
+        assert_speed(&mut attributes, 75.0);
+        hazards.update(FloorTile::Grass, 0.1, &mut attributes);
+        assert_speed(&mut attributes, 100.0);
+    }
+
+    #[test]
+    fn damage_accumulates_into_whole_points() {
+        let (mut hazards, mut attributes) = setup();
+        let damage: Vec<i16> = (0..5)
+            .map(|_| hazards.update(FloorTile::Thorns, 0.4, &mut attributes))
+            .collect();
+        assert_eq!(damage, [0, 0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn pending_damage_resets_off_hazards() {
+        let (mut hazards, mut attributes) = setup();
+        assert_eq!(hazards.update(FloorTile::Thorns, 0.8, &mut attributes), 0);
+        assert_eq!(hazards.update(FloorTile::Dirt, 0.1, &mut attributes), 0);
+        assert_eq!(hazards.update(FloorTile::Thorns, 0.4, &mut attributes), 0);
+    }
+}
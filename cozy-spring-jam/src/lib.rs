@@ -2,6 +2,7 @@ mod attribute;
 mod bullet;
 mod enemy;
 mod gun;
+mod hazard;
 mod player;
 
 use godot::prelude::*;
@@ -9,6 +10,7 @@ use godot::prelude::*;
 mod enemy_drop;
 mod room;
 mod utils;
+mod world;
 
 struct MyExtension;
 
@@ -1,16 +1,18 @@
 mod health_hud;
 
-use crate::attribute::Attributes;
+use crate::attribute::{Attributes, Effect, Operation, Stacking};
 use crate::gun::Gun;
+use crate::hazard::HazardTracker;
 use crate::player::health_hud::HealthHud;
 use crate::room::Room;
-use godot::builtin::{Vector2, real};
+use crate::world::World;
+use godot::builtin::{GString, Vector2, Vector2i, real};
 use godot::classes::{
     AnimatedSprite2D, Camera2D, CharacterBody2D, Control, ICamera2D, ICharacterBody2D, Input, Node,
     PackedScene, Timer,
 };
 use godot::global::{godot_print, pow, randf_range};
-use godot::obj::{Base, Gd, WithBaseField};
+use godot::obj::{Base, Gd, WithBaseField, WithUserSignals};
 use godot::prelude::{GodotClass, godot_api, load};
 use std::cmp::Ordering;
 
@@ -24,6 +26,10 @@ enum Orientation {
 pub enum PlayerAttribute {
     MaxHealth,
     Speed,
+    /// Seconds a dash lasts.
+    DashDuration,
+    /// Distance covered by a dash, derived from the speed and dash duration.
+    DashDistance,
 }
 
 #[derive(GodotClass)]
@@ -42,9 +48,11 @@ pub struct Player {
 
     attributes: Attributes<PlayerAttribute>,
 
+    hazards: HazardTracker<PlayerAttribute>,
+
     orientation: Orientation,
     health_scene: Gd<PackedScene>,
-    frames_since_last_healthbar_update: u16,
+    current_room: Option<Vector2i>,
     base: Base<CharacterBody2D>,
 }
 
@@ -54,12 +62,21 @@ impl Player {
         &mut self.attributes
     }
 
+    /// Emitted when the value of a player attribute changes, with the name of the attribute.
+    #[signal]
+    fn attribute_changed(attribute: GString, value: f32);
+
+    /// Emitted when a timed effect on the player, like a speed boost, runs out.
+    #[signal]
+    fn effect_expired(tag: GString);
+
+    /// Distance covered by a dash at the current speed.
+    #[func]
+    fn dash_distance(&mut self) -> f32 {
+        self.attr().get(PlayerAttribute::DashDistance)
+    }
+
     fn update_health_bar(&mut self) {
-        self.frames_since_last_healthbar_update += 1;
-        if self.frames_since_last_healthbar_update > 5 {
-            self.frames_since_last_healthbar_update = 0;
-            return;
-        }
         let mut hud_node = self
             .base_mut()
             .find_child("Hud")
@@ -149,32 +166,55 @@ impl Player {
             self.orientation = Orientation::Right;
         }
 
-        let new_position = self.base_mut().get_global_position();
-        let rooms = self
+        self.update_current_room();
+    }
+
+    fn update_hazards(&mut self, delta: f64) {
+        let Some(world) = World::for_node(self.base().upcast_ref()) else {
+            return;
+        };
+        let tile = world.bind().floor_at(self.base().get_global_position());
+        let damage = self
+            .hazards
+            .update(tile, delta as f32, &mut self.attributes);
+        // Damage over time keeps ticking during the invulnerability after a hit
+        if damage > 0 {
+            self.take_damage(damage);
+        }
+    }
+
+    fn update_current_room(&mut self) {
+        let Some(mut world) = World::for_node(self.base().upcast_ref()) else {
+            return;
+        };
+        let new_position = self.base().get_global_position();
+        let Some((coord, mut room)) = world.bind().room_containing(new_position) else {
+            return;
+        };
+        // Adjusting the camera for the current room
+        let mut camera_node: Gd<Camera2D> = self
             .base_mut()
-            .get_tree()
-            .expect("Could not retrieve tree")
-            .get_nodes_in_group("room");
-        for i in 0..rooms.len() {
-            let mut room: Gd<Room> = rooms.get(i).expect("Could not retrieve room!").cast();
-            if new_position.x > room.get_global_position().x
-                && new_position.x < room.get_global_position().x + 576.0
-                && new_position.y > room.get_global_position().y
-                && new_position.y < room.get_global_position().y + 352.0
-            {
-                // Generating adjacent rooms to the current room
-                room.bind_mut().generate_adjacent_rooms();
-                // Adjusting the camera for the current room
-                let mut camera_node: Gd<Camera2D> = self
-                    .base_mut()
-                    .find_child("Camera2D")
-                    .expect("Could not get camera node")
-                    .cast();
-                camera_node.set_global_position(room.get_global_position());
-                room.bind_mut().en_disable_enemies_in_room(true);
-            } else {
-                room.bind_mut().en_disable_enemies_in_room(false);
-            }
+            .find_child("Camera2D")
+            .expect("Could not get camera node")
+            .cast();
+        camera_node.set_global_position(room.get_global_position());
+        room.bind_mut().update_encounter(new_position);
+
+        if self.current_room == Some(coord) {
+            return;
+        }
+        self.current_room = Some(coord);
+        world.bind_mut().enter_room(coord);
+
+        // Generating adjacent rooms to the current room
+        room.bind_mut().generate_adjacent_rooms();
+        // Unloading rooms the player moved away from
+        world.bind_mut().stream_rooms(coord);
+
+        let rooms: Vec<(Vector2i, Gd<Room>)> = world.bind().rooms().collect();
+        for (room_coord, mut room) in rooms {
+            room.bind_mut()
+                .en_disable_enemies_in_room(room_coord == coord);
         }
     }
 
@@ -204,6 +244,12 @@ impl Player {
             godot_print!("Player on dmg cooldown!");
             return;
         }
+        self.take_damage(amount);
+        damage_cooldown_timer.start();
+    }
+
+    /// Deals `amount` damage to the player, ignoring the invulnerability after being hit.
+    fn take_damage(&mut self, amount: i16) {
         self.health -= amount;
         let mut camera: Gd<PlayerCamera> = self
             .base_mut()
@@ -220,7 +266,35 @@ impl Player {
         if self.health > self.attr().get_int(PlayerAttribute::MaxHealth) as i16 {
             self.health = self.attr().get(PlayerAttribute::MaxHealth) as i16;
         }
-        damage_cooldown_timer.start();
+        self.update_health_bar();
+    }
+
+    /// Sets the health of the player and refreshes the HUD. Setting the `health` property
+    /// directly leaves the HUD showing the old value.
+    #[func]
+    pub fn change_health(&mut self, health: i16) {
+        self.health = health;
+        self.update_health_bar();
+    }
+
+    /// Takes `amount` health as the price of something, unless the player can't afford it
+    /// without dying.
+    pub fn pay_health(&mut self, amount: i16) -> bool {
+        if self.health <= amount {
+            return false;
+        }
+        self.health -= amount;
+        self.update_health_bar();
+        true
+    }
+
+    /// Overrides the base max health of the player. Bonuses from pickups still apply on top.
+    #[func]
+    pub fn set_max_health(&mut self, value: f32) {
+        let mut effect = Effect::new();
+        effect.add_modifier(PlayerAttribute::MaxHealth, Operation::Set(value));
+        effect.set_tag("max_health_override", Stacking::Refresh);
+        self.attributes.apply_effect(effect);
     }
 }
 
@@ -231,11 +305,25 @@ impl ICharacterBody2D for Player {
         let mut attributes = Attributes::new();
         attributes.set_base(PlayerAttribute::MaxHealth, 20.0);
         attributes.set_base(PlayerAttribute::Speed, 150.0);
+        attributes
+            .set_base(PlayerAttribute::DashDuration, 0.2)
+            .derive(
+                PlayerAttribute::DashDistance,
+                &[PlayerAttribute::Speed, PlayerAttribute::DashDuration],
+                |inputs| match inputs {
+                    [speed, duration] => speed * duration,
+                    _ => 0.0,
+                },
+            )
+            .subscribe(PlayerAttribute::MaxHealth)
+            .subscribe(PlayerAttribute::Speed)
+            .subscribe(PlayerAttribute::DashDistance);
         Self {
             attributes,
+            hazards: HazardTracker::new(PlayerAttribute::Speed),
             health: 20,
             health_scene,
-            frames_since_last_healthbar_update: 1337,
+            current_room: None,
             animation: None,
             orientation: Orientation::Right,
             gun: None,
@@ -244,9 +332,11 @@ impl ICharacterBody2D for Player {
         }
     }
 
-    fn physics_process(&mut self, _delta: f64) {
-        self.update_health_bar();
+    fn physics_process(&mut self, delta: f64) {
+        self.tick_effects(delta);
+        self.handle_attribute_changes();
         self.position_gun();
+        self.update_hazards(delta);
         self.handle_walk_input();
         self.update_orientation();
         self.handle_shooting();
@@ -254,6 +344,7 @@ impl ICharacterBody2D for Player {
 
     fn ready(&mut self) {
         self.play_animation("default");
+        self.update_health_bar();
         if let Some(mut gun) = self.get_gun() {
             gun.bind_mut().set_is_player_gun(true);
         }
@@ -261,6 +352,24 @@ impl ICharacterBody2D for Player {
 }
 
 impl Player {
+    fn tick_effects(&mut self, delta: f64) {
+        for tag in self.attributes.tick_tags(delta as f32) {
+            self.signals().effect_expired().emit(&GString::from(tag));
+        }
+    }
+
+    fn handle_attribute_changes(&mut self) {
+        for (attr, value) in self.attributes.take_changes() {
+            if attr == PlayerAttribute::MaxHealth {
+                self.health = self.health.min(value as i16);
+                self.update_health_bar();
+            }
+            self.signals()
+                .attribute_changed()
+                .emit(&GString::from(format!("{attr:?}")), value);
+        }
+    }
+
     fn play_animation(&self, name: &str) {
         if let Some(mut anim) = self.get_animation() {
             anim.play_ex().name(name).done();
@@ -1,208 +1,36 @@
+pub mod biome;
+pub mod generation;
+pub mod map_format;
+mod navigation;
+pub mod prefab;
+pub mod prefab_resource;
+pub mod preset;
+pub mod state;
+mod validation;
+
 use crate::enemy::Enemy;
-use crate::utils::Grid;
+use crate::enemy_drop::EnemyDrop;
+use crate::room::biome::{Biome, BiomeMap};
+use crate::room::generation::{
+    Direction, FloorTile, GeneratedRoom, RoomGenParams, RoomGenerator, RoomLayout, RoomType,
+    WallTile,
+};
+use crate::room::navigation::bake_navigation_polygon;
+use crate::room::prefab::{Prefab, pick_prefab};
+use crate::room::prefab_resource::RoomPrefab;
+use crate::room::preset::RoomGenPreset;
+use crate::room::state::{DropState, EnemyState, RoomState};
+use crate::room::validation::{exit_cells, exit_distances, walkable_cells};
+use crate::utils::{Grid, mix_seed, room_seed};
+use crate::world::{World, seeded_rng};
+use godot::classes::file_access::ModeFlags;
 use godot::classes::node::ProcessMode;
 use godot::{
-    classes::{
-        FastNoiseLite, RandomNumberGenerator, TileMapLayer,
-        fast_noise_lite::{FractalType, NoiseType},
-    },
+    classes::{FileAccess, ITileMapLayer, NavigationRegion2D, RandomNumberGenerator, TileMapLayer},
     prelude::*,
 };
 use std::collections::HashMap;
 
-#[derive(Debug, Clone)]
-struct RoomGenParams {
-    growth_falloff: f32,
-    edge_growth: f32,
-    center_growth: f32,
-    growth_noise_amplitude: f32,
-    growth_noise_frequency: f32,
-    grow_noise_octaves: i32,
-    growth_noise_bias: f32,
-    special_noise_frequency: f32,
-    special_noise_amplitude: f32,
-    noise_fractal_gain: f32,
-    tree_growth_cutoff: f32,
-    lone_tree_growth_cutoff: f32,
-    lone_tree_special_cutoff: f32,
-    grass_growth_cutoff: f32,
-    tall_grass_growth_cutoff: f32,
-    exit_size: u32,
-}
-
-impl Default for RoomGenParams {
-    fn default() -> Self {
-        Self {
-            edge_growth: 1.5,
-            center_growth: -1.0,
-            growth_falloff: 0.6,
-            growth_noise_amplitude: 4.0,
-            growth_noise_frequency: 0.1,
-            growth_noise_bias: -1.7,
-            special_noise_frequency: 0.5,
-            special_noise_amplitude: 3.0,
-            grow_noise_octaves: 3,
-            noise_fractal_gain: 0.6,
-            tree_growth_cutoff: 0.6,
-            lone_tree_growth_cutoff: 0.4,
-            lone_tree_special_cutoff: 0.4,
-            grass_growth_cutoff: 0.0,
-            tall_grass_growth_cutoff: 0.2,
-            exit_size: 2,
-        }
-    }
-}
-
-#[derive(Clone)]
-struct RoomLayout {
-    exit_top: bool,
-    exit_bottom: bool,
-    exit_left: bool,
-    exit_right: bool,
-}
-
-struct SpecialField<'a> {
-    params: &'a RoomGenParams,
-    noise: Gd<FastNoiseLite>,
-}
-
-impl<'a> SpecialField<'a> {
-    fn new(seed: u32, params: &'a RoomGenParams) -> Self {
-        let mut noise = FastNoiseLite::new_gd();
-        noise.set_noise_type(NoiseType::VALUE);
-        noise.set_seed(seed as i32);
-        noise.set_frequency(params.special_noise_frequency);
-        Self { params, noise }
-    }
-
-    fn get_special_factor(&self, x: usize, y: usize) -> f32 {
-        let x = x as f32;
-        let y = y as f32;
-
-        self.params.special_noise_amplitude * self.noise.get_noise_2d(x, y)
-    }
-}
-
-struct GrowthField<'a> {
-    params: &'a RoomGenParams,
-    layout: &'a RoomLayout,
-    width: f32,
-    height: f32,
-    noise: Gd<FastNoiseLite>,
-    cache: HashMap<(usize, usize), f32>,
-}
-
-impl<'a> GrowthField<'a> {
-    fn new(
-        width: usize,
-        height: usize,
-        seed: u32,
-        params: &'a RoomGenParams,
-        layout: &'a RoomLayout,
-    ) -> Self {
-        let mut noise = FastNoiseLite::new_gd();
-        noise.set_noise_type(NoiseType::PERLIN);
-        noise.set_seed(seed as i32);
-        noise.set_frequency(params.growth_noise_frequency);
-        noise.set_fractal_octaves(params.grow_noise_octaves);
-        noise.set_fractal_type(FractalType::RIDGED);
-        noise.set_fractal_gain(params.noise_fractal_gain);
-        Self {
-            params,
-            layout,
-            width: width as f32 - 1.0,
-            height: height as f32 - 1.0,
-            noise,
-            cache: HashMap::new(),
-        }
-    }
-
-    fn get_edge_dist(&self, pos: Vector2) -> f32 {
-        let half_width = self.width / 2.0;
-        let half_height = self.height / 2.0;
-
-        let x_dist = (half_width - f32::abs(pos.x - half_width)).max(0.0);
-        let y_dist = (half_height - f32::abs(pos.y - half_height)).max(0.0);
-
-        f32::min(x_dist, y_dist)
-    }
-
-    fn is_on_critical_path(&self, pos_from_origin: Vector2, path: Vector2) -> bool {
-        let path_dir = path.normalized();
-        let dist_along_path = pos_from_origin.dot(path_dir);
-        if dist_along_path.is_sign_negative() {
-            return false;
-        }
-
-        let path_proportion = dist_along_path / path.length();
-        let dist_to_center_line = (pos_from_origin - dist_along_path * path_dir).length();
-
-        dist_to_center_line <= path_proportion * self.params.exit_size as f32
-    }
-
-    fn is_in_critical_section(&self, pos: Vector2) -> bool {
-        let origin = Vector2::new(self.width, self.height) / 2.0;
-        let pos_from_origin = pos - origin;
-
-        let path_to_bottom = Vector2::new(0.0, self.height) / 2.0;
-        let path_to_right = Vector2::new(self.width, 0.0) / 2.0;
-
-        if self.layout.exit_top && self.is_on_critical_path(pos_from_origin, -path_to_bottom) {
-            return true;
-        }
-        if self.layout.exit_bottom && self.is_on_critical_path(pos_from_origin, path_to_bottom) {
-            return true;
-        }
-        if self.layout.exit_left && self.is_on_critical_path(pos_from_origin, -path_to_right) {
-            return true;
-        }
-        if self.layout.exit_right && self.is_on_critical_path(pos_from_origin, path_to_right) {
-            return true;
-        }
-        false
-    }
-
-    fn get_const_component(&self, pos: Vector2) -> f32 {
-        let edge_dist = self.get_edge_dist(pos);
-
-        (self.params.edge_growth * (1.0 - edge_dist * self.params.growth_falloff))
-            .max(self.params.center_growth)
-    }
-
-    fn get_noise_component(&self, pos: Vector2) -> f32 {
-        self.params.growth_noise_amplitude * self.noise.get_noise_2d(pos.x, pos.y)
-            + self.params.growth_noise_bias
-    }
-
-    fn compute_growth_factor(&self, pos: Vector2) -> f32 {
-        if self.is_in_critical_section(pos) {
-            return f32::NEG_INFINITY;
-        }
-
-        (self.get_const_component(pos) + self.get_noise_component(pos)).clamp(-1.0, 1.0)
-    }
-
-    fn get_growth_factor(&mut self, x: usize, y: usize) -> f32 {
-        let pos = Vector2::new(x as f32, y as f32);
-
-        if let Some(cached_value) = self.cache.get(&(x, y)) {
-            return *cached_value;
-        }
-        let value = self.compute_growth_factor(pos);
-        self.cache.insert((x, y), value);
-        value
-    }
-}
-
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
-enum FloorTile {
-    #[default]
-    None,
-    Dirt,
-    Grass,
-    TallGrass,
-}
-
 #[derive(GodotClass)]
 #[class(base=TileMapLayer, init)]
 struct FloorLayer {
@@ -221,67 +49,98 @@ struct FloorLayer {
     #[export]
     tall_grass_coords: Vector2i,
 
+    #[export]
+    flowers_coords: Vector2i,
+
+    /// Alternative tile of `flowers_coords`, which lets flowers reuse a tinted grass tile.
+    #[export]
+    flowers_alternative: i32,
+
+    #[export]
+    thorns_coords: Vector2i,
+
+    #[export]
+    thorns_alternative: i32,
+
+    #[export]
+    water_terrain: i32,
+
+    #[export]
+    mud_terrain: i32,
+
+    /// Dirt terrain of each biome, in the order of `Biome`. Falls back to `dirt_terrain`.
+    #[export]
+    biome_dirt_terrains: PackedInt32Array,
+
     base: Base<TileMapLayer>,
 }
 
+#[godot_api]
+impl ITileMapLayer for FloorLayer {
+    fn ready(&mut self) {
+        // Hazards slow and hurt whatever crosses them, so they must not look like safe ground
+        if self.water_terrain == self.dirt_terrain || self.mud_terrain == self.dirt_terrain {
+            godot_warn!("FloorLayer draws water or mud with the dirt terrain!");
+        }
+        if self.thorns_coords == self.tall_grass_coords && self.thorns_alternative == 0 {
+            godot_warn!("FloorLayer draws thorns with the tall grass tile!");
+        }
+    }
+}
+
 impl FloorLayer {
-    fn set_tiles(&mut self, grid: &Grid<FloorTile>) {
+    fn set_tiles(&mut self, grid: &Grid<FloorTile>, biome: Biome) {
         self.base_mut().clear();
 
         let mut regions: HashMap<FloorTile, Array<Vector2i>> = HashMap::new();
         for (pos, tile) in grid {
             match tile {
                 FloorTile::None => (),
-                FloorTile::Grass => self.place_grass(pos),
-                FloorTile::TallGrass => self.place_tall_grass(pos),
-                FloorTile::Dirt => regions.entry(*tile).or_default().push_front(pos),
+                FloorTile::Grass => self.place_atlas_tile(pos, self.grass_coords, 0),
+                FloorTile::TallGrass => self.place_atlas_tile(pos, self.tall_grass_coords, 0),
+                FloorTile::Flowers => {
+                    self.place_atlas_tile(pos, self.flowers_coords, self.flowers_alternative)
+                }
+                FloorTile::Thorns => {
+                    self.place_atlas_tile(pos, self.thorns_coords, self.thorns_alternative)
+                }
+                FloorTile::Dirt | FloorTile::Water | FloorTile::Mud => {
+                    regions.entry(*tile).or_default().push_front(pos)
+                }
             }
         }
 
         for (tile, cells) in regions {
-            match tile {
-                FloorTile::Dirt => self.place_dirt(&cells),
-                _ => (),
-            }
+            let terrain = match tile {
+                FloorTile::Dirt => self
+                    .biome_dirt_terrains
+                    .get(biome as usize)
+                    .unwrap_or(self.dirt_terrain),
+                FloorTile::Water => self.water_terrain,
+                FloorTile::Mud => self.mud_terrain,
+                _ => continue,
+            };
+            self.place_terrain(&cells, terrain);
         }
     }
 
-    fn place_grass(&mut self, pos: Vector2i) {
+    fn place_atlas_tile(&mut self, pos: Vector2i, coords: Vector2i, alternative: i32) {
         let source = self.tile_source;
-        let coords = self.grass_coords;
         self.base_mut()
             .set_cell_ex(pos)
             .source_id(source)
             .atlas_coords(coords)
+            .alternative_tile(alternative)
             .done();
     }
 
-    fn place_tall_grass(&mut self, pos: Vector2i) {
-        let source = self.tile_source;
-        let coords = self.tall_grass_coords;
-        self.base_mut()
-            .set_cell_ex(pos)
-            .source_id(source)
-            .atlas_coords(coords)
-            .done();
-    }
-
-    fn place_dirt(&mut self, cells: &Array<Vector2i>) {
+    fn place_terrain(&mut self, cells: &Array<Vector2i>, terrain: i32) {
         let terrain_set = self.dirt_terrain_set;
-        let terrain = self.dirt_terrain;
         self.base_mut()
             .set_cells_terrain_connect(&cells, terrain_set, terrain);
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
-enum WallTile {
-    #[default]
-    Clear,
-    Wall,
-    LoneTree,
-}
-
 #[derive(GodotClass)]
 #[class(base=TileMapLayer, init)]
 struct WallsLayer {
@@ -300,53 +159,85 @@ struct WallsLayer {
     #[export]
     lone_tree_coords: Vector2i,
 
+    #[export]
+    rock_coords: Vector2i,
+
+    /// Alternative tile of `rock_coords`.
+    #[export]
+    rock_alternative: i32,
+
+    /// Wall terrain of each biome, in the order of `Biome`. Falls back to `wall_terrain`.
+    #[export]
+    biome_wall_terrains: PackedInt32Array,
+
     base: Base<TileMapLayer>,
 }
 
 impl WallsLayer {
-    fn set_tiles(&mut self, grid: &Grid<WallTile>) {
+    fn set_tiles(&mut self, grid: &Grid<WallTile>, biome: Biome) {
         self.base_mut().clear();
 
         let mut regions: HashMap<WallTile, Array<Vector2i>> = HashMap::new();
         for (pos, tile) in grid {
             match tile {
-                WallTile::Clear => self.place_clear(pos),
-                WallTile::LoneTree => self.place_lone_tree(pos),
                 WallTile::Wall => regions.entry(*tile).or_default().push_front(pos),
+                _ => self.place_tile(pos, *tile),
             }
         }
 
         for (tile, cells) in regions {
             match tile {
-                WallTile::Wall => self.place_walls(&cells),
+                WallTile::Wall => self.place_walls(&cells, biome),
                 _ => (),
             }
         }
     }
 
-    fn place_clear(&mut self, pos: Vector2i) {
-        let source = self.tile_source;
-        let coords = self.clear_coords;
-        self.base_mut()
-            .set_cell_ex(pos)
-            .source_id(source)
-            .atlas_coords(coords)
-            .done();
+    /// Redraws `cells` after their tiles changed in `grid`, reconnecting the walls around them
+    /// without touching the rest of the layer.
+    fn update_cells(&mut self, grid: &Grid<WallTile>, cells: &[Vector2i], biome: Biome) {
+        let mut walls: Vec<Vector2i> = Vec::new();
+        for cell in cells {
+            for pos in std::iter::once(*cell).chain(grid.neighbours_8(*cell)) {
+                match grid.get(pos) {
+                    WallTile::Wall if !walls.contains(&pos) => walls.push(pos),
+                    WallTile::Wall => (),
+                    tile => self.place_tile(pos, *tile),
+                }
+            }
+        }
+        if !walls.is_empty() {
+            let walls: Array<Vector2i> = walls.into_iter().collect();
+            self.place_walls(&walls, biome);
+        }
     }
 
-    fn place_lone_tree(&mut self, pos: Vector2i) {
+    /// Places the atlas tile of every tile except walls, which are drawn as terrain.
+    fn place_tile(&mut self, pos: Vector2i, tile: WallTile) {
+        match tile {
+            WallTile::Clear => self.place_atlas_tile(pos, self.clear_coords, 0),
+            WallTile::LoneTree => self.place_atlas_tile(pos, self.lone_tree_coords, 0),
+            WallTile::Rock => self.place_atlas_tile(pos, self.rock_coords, self.rock_alternative),
+            WallTile::Wall => (),
+        }
+    }
+
+    fn place_atlas_tile(&mut self, pos: Vector2i, coords: Vector2i, alternative: i32) {
         let source = self.tile_source;
-        let coords = self.lone_tree_coords;
         self.base_mut()
             .set_cell_ex(pos)
             .source_id(source)
             .atlas_coords(coords)
+            .alternative_tile(alternative)
             .done();
     }
 
-    fn place_walls(&mut self, cells: &Array<Vector2i>) {
+    fn place_walls(&mut self, cells: &Array<Vector2i>, biome: Biome) {
         let terrain_set = self.wall_terrain_set;
-        let terrain = self.wall_terrain;
+        let terrain = self
+            .biome_wall_terrains
+            .get(biome as usize)
+            .unwrap_or(self.wall_terrain);
         self.base_mut()
             .set_cells_terrain_connect(&cells, terrain_set, terrain);
     }
@@ -361,24 +252,90 @@ pub struct Room {
     #[export]
     walls_layer: Option<Gd<WallsLayer>>,
 
+    #[export]
+    navigation_region: Option<Gd<NavigationRegion2D>>,
+
+    /// Radius of the agents walking through the room, kept clear of walls when baking.
+    #[export]
+    navigation_agent_radius: f32,
+
     #[export]
     width: i32,
 
     #[export]
     height: i32,
 
-    params: RoomGenParams,
+    /// Generation presets, one of which is picked per room from its seed.
+    #[export]
+    presets: Array<Gd<RoomGenPreset>>,
+
+    /// Hand-authored rooms and chunks stamped into generated rooms.
+    #[export]
+    prefabs: Array<Gd<RoomPrefab>>,
+
+    /// Chance of a room using one of the prefabs that aren't made for a specific room type.
+    #[export]
+    prefab_chance: f32,
 
     room_scene: Gd<PackedScene>,
 
     enemy_scenes: Vec<Gd<PackedScene>>,
 
+    boss_scene: Gd<PackedScene>,
+
+    treasure_scene: Gd<PackedScene>,
+
+    shop_item_scenes: Vec<Gd<PackedScene>>,
+
+    room_type: RoomType,
+
     room_layout: Option<RoomLayout>,
 
-    adjacent_rooms_generated: bool,
+    floor_grid: Grid<FloorTile>,
+
+    wall_grid: Grid<WallTile>,
+
+    /// Remaining hit points of every destructible wall cell.
+    wall_health: Grid<Option<f32>>,
+
+    destroyed_walls: Vec<Vector2i>,
+
+    biome: Biome,
+
+    /// Loot that destroyed walls can drop.
+    #[export]
+    wall_loot: Option<Gd<PackedScene>>,
+
+    /// Chance of a destroyed wall dropping `wall_loot`, between 0 and 1.
+    #[export]
+    wall_loot_chance: f32,
+
+    /// Most waves of enemies that follow the first one in a combat room.
+    #[export]
+    max_waves: i32,
 
+    /// Dropped in the middle of the room once all of its enemies are defeated.
     #[export]
-    not_first_room: bool,
+    clear_reward: Option<Gd<PackedScene>>,
+
+    coord: Vector2i,
+
+    run_seed: u64,
+
+    seed: u64,
+
+    cleared: bool,
+
+    waves_left: u32,
+
+    /// Whether the player is fighting the enemies of the room behind closed exits.
+    encounter_active: bool,
+
+    /// Exit cells walled off during an encounter.
+    locked_exits: Vec<Vector2i>,
+
+    /// Whether the navigation polygon is waiting to be rebaked at the end of the frame.
+    navigation_outdated: bool,
 
     base: Base<Node2D>,
 }
@@ -389,320 +346,731 @@ impl INode2D for Room {
         Self {
             floor_layer: None,
             walls_layer: None,
+            navigation_region: None,
+            navigation_agent_radius: 10.0,
             width: 0,
             height: 0,
-            params: RoomGenParams::default(),
+            presets: Array::new(),
+            prefabs: Array::new(),
+            prefab_chance: 0.0,
             room_scene: load("res://scenes/room_scene.tscn"),
             enemy_scenes: vec![
                 load("res://scenes/npcs/enemies/basic_melee_enemy.tscn"),
                 load("res://scenes/npcs/enemies/basic_ranged_enemy.tscn"),
             ],
+            boss_scene: load("res://scenes/npcs/enemies/boss_enemy.tscn"),
+            treasure_scene: load("res://scenes/rooms/treasure_chest.tscn"),
+            shop_item_scenes: vec![
+                load("res://scenes/rooms/shop/shop_heart_container.tscn"),
+                load("res://scenes/rooms/shop/shop_speed_boost.tscn"),
+            ],
+            room_type: RoomType::Normal,
             room_layout: None,
-            adjacent_rooms_generated: false,
-            not_first_room: true,
+            floor_grid: Grid::default(),
+            wall_grid: Grid::default(),
+            wall_health: Grid::default(),
+            destroyed_walls: Vec::new(),
+            biome: Biome::default(),
+            wall_loot: None,
+            wall_loot_chance: 0.0,
+            max_waves: 0,
+            clear_reward: None,
+            coord: Vector2i::ZERO,
+            run_seed: 0,
+            seed: 0,
+            cleared: false,
+            waves_left: 0,
+            encounter_active: false,
+            locked_exits: Vec::new(),
+            navigation_outdated: false,
             base,
         }
     }
 
     fn ready(&mut self) {
-        if !self.not_first_room {
-            let mut rng = RandomNumberGenerator::new_gd();
-            let layout = Option::from(RoomLayout {
-                exit_top: rng.randi() % 2 == 0,
-                exit_left: rng.randi() % 2 == 0,
-                exit_bottom: rng.randi() % 2 == 0,
-                exit_right: rng.randi() % 2 == 0,
-            });
-            self.generate(
-                rng.randi(),
-                &layout.clone().expect("Could not clone room layout"),
+        let Some(mut world) = World::for_node(self.base().upcast_ref()) else {
+            return;
+        };
+        // Rooms placed in the scene instead of spawned by a neighbour start the run
+        if self.room_layout.is_none() {
+            self.build_from_world(&mut world, Vector2i::ZERO);
+        }
+        let pixel_size = self.pixel_size();
+        world
+            .bind_mut()
+            .register_room(self.coord, self.to_gd(), pixel_size);
+    }
+}
+
+#[godot_api]
+impl Room {
+    /// Bakes the navigation polygon of the room from its current walls.
+    #[func]
+    fn rebuild_navigation(&mut self) {
+        self.navigation_outdated = false;
+        let tile_size = self.tile_size();
+        let agent_radius = self.navigation_agent_radius;
+        if let Some(region) = &mut self.navigation_region {
+            let polygon = bake_navigation_polygon(&self.wall_grid, tile_size, agent_radius);
+            region.set_navigation_polygon(&polygon);
+        }
+    }
+
+    /// Returns the tiles of the room in the ASCII map format.
+    #[func]
+    fn dump_map(&self) -> GString {
+        self.generated_room().to_ascii().as_str().into()
+    }
+
+    /// Saves the tiles of the room, as an ASCII map if `path` ends in `.txt` and in the binary
+    /// map format otherwise.
+    #[func]
+    fn save_map(&self, path: GString) -> bool {
+        let Some(mut file) = FileAccess::open(&path, ModeFlags::WRITE) else {
+            godot_error!("Could not open {} to save the room map!", path);
+            return false;
+        };
+        let room = self.generated_room();
+        if path.to_string().ends_with(".txt") {
+            file.store_string(room.to_ascii().as_str());
+        } else {
+            file.store_buffer(&PackedByteArray::from(room.to_bytes().as_slice()));
+        }
+        true
+    }
+
+    /// Replaces the tiles of the room with a map saved by `save_map`.
+    #[func]
+    fn load_map(&mut self, path: GString) -> bool {
+        let result = if path.to_string().ends_with(".txt") {
+            GeneratedRoom::from_ascii(&FileAccess::get_file_as_string(&path).to_string())
+        } else {
+            GeneratedRoom::from_bytes(FileAccess::get_file_as_bytes(&path).as_slice())
+        };
+        let mut room = match result {
+            Ok(room) => room,
+            Err(err) => {
+                godot_error!("Could not load room map {}: {}", path, err);
+                return false;
+            }
+        };
+        let size = (self.width as usize, self.height as usize);
+        if (room.walls.width(), room.walls.height()) != size
+            || (room.floor.width(), room.floor.height()) != size
+        {
+            godot_error!(
+                "Room map {} does not fit a {}x{} room!",
+                path,
+                self.width,
+                self.height
             );
-            self.room_layout = layout;
+            return false;
         }
+        room.biome = self.biome;
+        self.apply_generated(room);
+        true
     }
 }
 
 impl Room {
-    fn generate(&mut self, seed: u32, layout: &RoomLayout) {
-        let mut growth = GrowthField::new(
-            self.width as usize,
-            self.height as usize,
-            seed,
-            &self.params,
-            &layout,
+    const TERRAIN_SEED_STREAM: u64 = 2;
+    const ENEMY_SEED_STREAM: u64 = 3;
+    const PRESET_SEED_STREAM: u64 = 4;
+    const PREFAB_SEED_STREAM: u64 = 5;
+    const WAVE_SEED_STREAM: u64 = 6;
+    const LOOT_SEED_STREAM: u64 = 7;
+    const MIN_SPAWN_EXIT_DISTANCE: u32 = 4;
+    const DEFAULT_TILE_SIZE: Vector2i = Vector2i::new(32, 32);
+
+    fn tile_size(&self) -> Vector2i {
+        self.walls_layer
+            .as_ref()
+            .and_then(|layer| layer.get_tile_set())
+            .map_or(Self::DEFAULT_TILE_SIZE, |tile_set| tile_set.get_tile_size())
+    }
+
+    /// Size of the room in pixels, derived from its size in tiles.
+    pub fn pixel_size(&self) -> Vector2 {
+        let tile_size = self.tile_size();
+        Vector2::new(
+            (self.width * tile_size.x) as real,
+            (self.height * tile_size.y) as real,
+        )
+    }
+
+    /// Position of the center of the tile at `cell`, relative to the room.
+    fn cell_center(&self, cell: Vector2i) -> Vector2 {
+        let tile_size = self.tile_size();
+        Vector2::new(
+            ((cell.x * tile_size.x) + tile_size.x / 2) as real,
+            ((cell.y * tile_size.y) + tile_size.y / 2) as real,
+        )
+    }
+
+    /// Returns the cell under the global position `pos`, if it is inside the room.
+    fn cell_at(&self, pos: Vector2) -> Option<Vector2i> {
+        let tile_size = self.tile_size();
+        let local = self.base().to_local(pos);
+        let cell = Vector2i::new(
+            (local.x / tile_size.x as real).floor() as i32,
+            (local.y / tile_size.y as real).floor() as i32,
         );
-        let special = SpecialField::new(seed, &self.params);
+        self.wall_grid.contains(cell).then_some(cell)
+    }
 
-        let mut floor_grid: Grid<FloorTile> = Grid::new(self.width as usize, self.height as usize);
-        self.generate_floor(&mut floor_grid, &mut growth);
+    /// Whether no wall blocks the view between the global positions `from` and `to`. Positions
+    /// outside of the room are always visible.
+    pub fn has_line_of_sight(&self, from: Vector2, to: Vector2) -> bool {
+        let (Some(from), Some(to)) = (self.cell_at(from), self.cell_at(to)) else {
+            return true;
+        };
+        self.wall_grid
+            .line_of_sight(from, to, |tile| *tile == WallTile::Clear)
+    }
 
-        let mut wall_grid: Grid<WallTile> = Grid::new(self.width as usize, self.height as usize);
+    /// Returns the floor tile under the global position `pos`.
+    pub fn floor_at(&self, pos: Vector2) -> FloorTile {
+        self.cell_at(pos)
+            .and_then(|cell| self.floor_grid.try_get(cell))
+            .copied()
+            .unwrap_or_default()
+    }
 
-        self.place_special(&mut wall_grid, &mut growth, &special);
-        self.place_walls(&mut wall_grid, &mut growth, &layout);
+    /// Moves the room to the grid coordinate `coord` and derives its seed from the run seed.
+    fn place_at(&mut self, run_seed: u64, coord: Vector2i) {
+        self.coord = coord;
+        self.run_seed = run_seed;
+        self.seed = room_seed(run_seed, coord);
+    }
 
+    fn stream_rng(&self, stream: u64) -> Gd<RandomNumberGenerator> {
+        seeded_rng(mix_seed(self.seed, stream))
+    }
+
+    /// Generates the room at `coord` as planned by the world and fills it with its content.
+    fn build_from_world(&mut self, world: &mut Gd<World>, coord: Vector2i) {
+        let (run_seed, layout, room_type) = {
+            let mut world = world.bind_mut();
+            (
+                world.run_seed(),
+                world.room_layout(coord),
+                world.room_type(coord),
+            )
+        };
+        self.place_at(run_seed, coord);
+        self.room_type = room_type;
+        self.cleared = !room_type.has_combat();
+        self.generate(&layout);
+        self.room_layout = Some(layout);
+        self.spawn_content();
+        if !self.cleared {
+            let mut rng = self.stream_rng(Self::WAVE_SEED_STREAM);
+            self.waves_left = rng.randi_range(0, self.max_waves.max(0)) as u32;
+        }
+    }
+
+    /// Picks one of the presets for a room in `biome`. Presets made for the biome take priority
+    /// over the ones used everywhere.
+    fn pick_params(&self, biome: Biome) -> RoomGenParams {
+        let with_biome = |only: Option<Biome>| -> Vec<Gd<RoomGenPreset>> {
+            self.presets
+                .iter_shared()
+                .filter(|preset| preset.bind().biome() == only)
+                .collect()
+        };
+        let mut candidates = with_biome(Some(biome));
+        if candidates.is_empty() {
+            candidates = with_biome(None);
+        }
+        if candidates.is_empty() {
+            return RoomGenParams::default();
+        }
+        let mut rng = self.stream_rng(Self::PRESET_SEED_STREAM);
+        let idx = rng.randi_range(0, candidates.len() as i32 - 1);
+        candidates[idx as usize].bind().to_params()
+    }
+
+    /// Picks a prefab for the room, if any. Prefabs made for its room type are always used.
+    fn pick_prefab(&self, layout: &RoomLayout) -> Option<Prefab> {
+        let resources: Vec<Gd<RoomPrefab>> = self.prefabs.iter_shared().collect();
+        let bound: Vec<_> = resources.iter().map(|prefab| prefab.bind()).collect();
+        let prefabs = bound.iter().filter_map(|prefab| prefab.prefab());
+        let mut rng = self.stream_rng(Self::PREFAB_SEED_STREAM);
+        let prefab = pick_prefab(prefabs, layout, self.room_type, rng.randf())?;
+        if prefab.room_type.is_none() && rng.randf() >= self.prefab_chance {
+            return None;
+        }
+        Some(prefab.clone())
+    }
+
+    fn generate(&mut self, layout: &RoomLayout) {
+        let seed = mix_seed(self.seed, Self::TERRAIN_SEED_STREAM) as u32;
+        let biomes = BiomeMap::new(self.run_seed);
+        let biome = biomes.biome_at_cell(
+            self.coord,
+            self.width as usize,
+            self.height as usize,
+            self.center_cell(),
+        );
+        let params = self.pick_params(biome).for_room_type(self.room_type);
+        let prefab = self.pick_prefab(layout);
+        let mut generator =
+            RoomGenerator::new(self.width, self.height, &params).with_biomes(&biomes, self.coord);
+        if let Some(prefab) = &prefab {
+            generator = generator.with_prefab(prefab);
+        }
+        let room = generator.generate(seed, layout);
+        self.apply_generated(room);
+    }
+
+    /// Shows the tiles of `room` and makes them the current state of the room.
+    fn apply_generated(&mut self, room: GeneratedRoom) {
         if let Some(floor_layer) = &mut self.floor_layer {
-            floor_layer.bind_mut().set_tiles(&floor_grid);
+            floor_layer.bind_mut().set_tiles(&room.floor, room.biome);
         }
 
         if let Some(walls_layer) = &mut self.walls_layer {
-            walls_layer.bind_mut().set_tiles(&wall_grid);
+            walls_layer.bind_mut().set_tiles(&room.walls, room.biome);
         }
+        self.floor_grid = room.floor;
+        self.wall_grid = room.walls;
+        self.biome = room.biome;
+        self.reset_wall_health();
+        self.rebuild_navigation();
     }
 
-    fn generate_floor(&self, grid: &mut Grid<FloorTile>, growth: &mut GrowthField) {
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let pos = Vector2i::new(x, y);
-                let growth_factor = growth.get_growth_factor(x as usize, y as usize);
+    fn generated_room(&self) -> GeneratedRoom {
+        GeneratedRoom {
+            floor: self.floor_grid.clone(),
+            walls: self.wall_grid.clone(),
+            biome: self.biome,
+        }
+    }
 
-                if growth_factor >= self.params.tall_grass_growth_cutoff {
-                    grid.set(pos, FloorTile::TallGrass);
-                } else if growth_factor >= self.params.grass_growth_cutoff {
-                    grid.set(pos, FloorTile::Grass);
-                } else {
-                    grid.set(pos, FloorTile::Dirt);
-                }
-            }
+    /// Rebakes the navigation polygon at the end of the frame, once for all walls that changed
+    /// until then.
+    fn queue_navigation_rebuild(&mut self) {
+        if self.navigation_outdated {
+            return;
         }
+        self.navigation_outdated = true;
+        self.base_mut().call_deferred("rebuild_navigation", &[]);
+    }
+
+    /// Gives every destructible wall inside the room its full hit points. The border stays
+    /// indestructible so nobody can leave the room except through its exits.
+    fn reset_wall_health(&mut self) {
+        self.wall_health = self.wall_grid.map(|pos, tile| {
+            let is_border =
+                pos.x == 0 || pos.y == 0 || pos.x == self.width - 1 || pos.y == self.height - 1;
+            if is_border { None } else { tile.max_health() }
+        });
+        self.destroyed_walls.clear();
     }
 
-    fn place_special(
-        &self,
-        grid: &mut Grid<WallTile>,
-        growth: &mut GrowthField,
-        special: &SpecialField,
-    ) {
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let pos = Vector2i::new(x, y);
-                let growth_factor = growth.get_growth_factor(x as usize, y as usize);
-                let special_factor = special.get_special_factor(x as usize, y as usize);
+    /// Returns the destructible wall cell closest to the global position `pos`, looking at the
+    /// cell under it and its neighbours.
+    fn wall_cell_near(&self, pos: Vector2) -> Option<Vector2i> {
+        let center = self.cell_at(pos)?;
+        let local = self.base().to_local(pos);
+        let cells = std::iter::once(center)
+            .chain(self.wall_health.neighbours_8(center))
+            .filter(|cell| self.wall_health.get(*cell).is_some());
+        cells.min_by(|a, b| {
+            let dist_a = self.cell_center(*a).distance_squared_to(local);
+            let dist_b = self.cell_center(*b).distance_squared_to(local);
+            dist_a.total_cmp(&dist_b)
+        })
+    }
 
-                if special_factor >= self.params.lone_tree_special_cutoff
-                    && growth_factor >= self.params.lone_tree_growth_cutoff
-                {
-                    grid.set(pos, WallTile::LoneTree);
-                }
-            }
+    /// Deals `power` damage to the wall hit at the global position `pos`, destroying it when it
+    /// runs out of hit points.
+    pub fn damage_wall(&mut self, pos: Vector2, power: f32) {
+        let Some(cell) = self.wall_cell_near(pos) else {
+            return;
+        };
+        let Some(remaining) = self.wall_health.get_mut(cell) else {
+            return;
+        };
+        *remaining -= power;
+        if *remaining > 0.0 {
+            return;
         }
+        self.destroy_wall(cell);
+        self.drop_wall_loot(cell);
     }
 
-    fn place_walls(
-        &self,
-        grid: &mut Grid<WallTile>,
-        growth: &mut GrowthField,
-        layout: &RoomLayout,
-    ) {
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let pos = Vector2i::new(x, y);
-                let growth_factor = growth.get_growth_factor(x as usize, y as usize);
+    fn destroy_wall(&mut self, cell: Vector2i) {
+        self.clear_wall(cell);
+        self.redraw_walls(&[cell]);
+    }
 
-                if self.should_have_edge_wall(x, y, layout)
-                    || growth_factor >= self.params.tree_growth_cutoff
-                {
-                    grid.set(pos, WallTile::Wall);
-                }
-            }
+    /// Removes the wall at `cell` without redrawing, so several walls can be removed at once.
+    fn clear_wall(&mut self, cell: Vector2i) {
+        self.wall_health.set(cell, None);
+        self.wall_grid.set(cell, WallTile::Clear);
+        self.destroyed_walls.push(cell);
+    }
+
+    /// Redraws the walls after `cells` changed, which lets the terrain reconnect around them.
+    fn redraw_walls(&mut self, cells: &[Vector2i]) {
+        let biome = self.biome;
+        if let Some(walls_layer) = &mut self.walls_layer {
+            walls_layer
+                .bind_mut()
+                .update_cells(&self.wall_grid, cells, biome);
         }
+        self.queue_navigation_rebuild();
     }
 
-    fn should_have_edge_wall(&self, x: i32, y: i32, layout: &RoomLayout) -> bool {
-        let center_dist_x = x - self.width / 2;
-        let center_dist_y = y - self.height / 2;
-        let max_dist = self.params.exit_size as i32 / 2;
+    fn drop_wall_loot(&mut self, cell: Vector2i) {
+        let Some(scene) = self.wall_loot.clone() else {
+            return;
+        };
+        let seed = mix_seed(
+            mix_seed(self.seed, Self::LOOT_SEED_STREAM),
+            mix_seed(cell.x as u64, cell.y as u64),
+        );
+        if seeded_rng(seed).randf() < self.wall_loot_chance {
+            self.add_item(&scene, cell);
+        }
+    }
 
-        if layout.exit_top && center_dist_x.abs() <= max_dist && center_dist_y.is_negative() {
-            return false;
+    /// Spawns all neighbouring rooms that are not loaded yet, restoring the ones that were
+    /// unloaded before.
+    pub fn generate_adjacent_rooms(&mut self) {
+        let Some(layout) = self.room_layout.clone() else {
+            godot_error!("Room layout not stored!");
+            return;
+        };
+        let Some(mut world) = World::for_node(self.base().upcast_ref()) else {
+            return;
+        };
+        godot_print!(
+            "Generating adjacent rooms to {:?}",
+            self.base().get_global_position()
+        );
+        for dir in layout.exits() {
+            self.spawn_adjacent_room(&mut world, dir);
         }
-        if layout.exit_bottom && center_dist_x.abs() <= max_dist && center_dist_y.is_positive() {
-            return false;
+    }
+
+    fn spawn_adjacent_room(&mut self, world: &mut Gd<World>, dir: Direction) {
+        let offset = dir.offset();
+        let new_room_coord = self.coord + offset;
+        if world.bind().has_room(new_room_coord) {
+            return;
         }
-        if layout.exit_left && center_dist_y.abs() <= max_dist && center_dist_x.is_negative() {
-            return false;
+        let new_room_position = self.base().get_position()
+            + Vector2::new(offset.x as real, offset.y as real) * self.pixel_size();
+
+        let mut new_room_node: Gd<Room> = self
+            .room_scene
+            .instantiate()
+            .expect("Could not be instantiated!")
+            .cast();
+        new_room_node.set_position(new_room_position);
+
+        let saved_state = world.bind_mut().take_room_state(new_room_coord);
+        let mut new_room = new_room_node.bind_mut();
+        if let Some(state) = saved_state {
+            let run_seed = world.bind_mut().run_seed();
+            new_room.place_at(run_seed, new_room_coord);
+            new_room.restore_state(state);
+        } else {
+            new_room.build_from_world(world, new_room_coord);
         }
-        if layout.exit_right && center_dist_y.abs() <= max_dist && center_dist_x.is_positive() {
-            return false;
+        drop(new_room);
+        self.base_mut()
+            .get_parent()
+            .expect("Could not get parent!")
+            .add_child(&new_room_node);
+        godot_print!(
+            "Generated new room at {:?}",
+            new_room_node.get_global_position()
+        );
+    }
+
+    fn spawn_content(&mut self) {
+        match self.room_type {
+            RoomType::Start | RoomType::Safe => (),
+            RoomType::Normal => self.spawn_enemies_in_room(Self::ENEMY_SEED_STREAM, 0),
+            RoomType::Treasure => self.spawn_treasure(),
+            RoomType::Shop => self.spawn_shop(),
+            RoomType::Boss => self.spawn_boss(),
         }
+    }
 
-        let is_edge = y == 0 || y == self.height - 1 || x == 0 || x == self.width - 1;
-        is_edge
+    /// Returns the walkable cell closest to `target`.
+    fn nearest_walkable_cell(&self, target: Vector2i) -> Option<Vector2i> {
+        walkable_cells(&self.wall_grid)
+            .into_iter()
+            .min_by_key(|cell| {
+                let dist = *cell - target;
+                dist.x * dist.x + dist.y * dist.y
+            })
     }
 
-    pub fn generate_adjacent_rooms(&mut self) {
-        if self.adjacent_rooms_generated {
+    fn center_cell(&self) -> Vector2i {
+        Vector2i::new(self.width / 2, self.height / 2)
+    }
+
+    fn add_item(&mut self, scene: &Gd<PackedScene>, cell: Vector2i) {
+        let mut item: Gd<Node2D> = scene
+            .instantiate()
+            .expect("Could not instantiate item scene!")
+            .cast();
+        self.npcs_node().add_child(&item);
+        item.set_position(self.cell_center(cell));
+    }
+
+    fn spawn_treasure(&mut self) {
+        let Some(cell) = self.nearest_walkable_cell(self.center_cell()) else {
             return;
+        };
+        let scene = self.treasure_scene.clone();
+        self.add_item(&scene, cell);
+    }
+
+    /// Lays out the shop items in a row through the middle of the room. They cost health and
+    /// are bought through the `interact` action.
+    fn spawn_shop(&mut self) {
+        let scenes = self.shop_item_scenes.clone();
+        let center = self.center_cell();
+        let first = center.x - scenes.len() as i32 + 1;
+        for (i, scene) in scenes.iter().enumerate() {
+            let target = Vector2i::new(first + 2 * i as i32, center.y);
+            if let Some(cell) = self.nearest_walkable_cell(target) {
+                self.add_item(scene, cell);
+            }
         }
-        let layout_raw = self.room_layout.clone();
-        match layout_raw {
-            None => {
-                godot_error!("Room layout not stored!")
+    }
+
+    fn spawn_boss(&mut self) {
+        let Some(cell) = self.nearest_walkable_cell(self.center_cell()) else {
+            return;
+        };
+        let mut rng = self.stream_rng(Self::ENEMY_SEED_STREAM);
+        let gun_seed = ((rng.randi() as u64) << 32) | rng.randi() as u64;
+        let scene = self.boss_scene.clone();
+        let position = self.cell_center(cell);
+        self.add_enemy(&scene, position, Some(gun_seed));
+    }
+
+    /// Spawns between `min_amount` and six enemies, placed by the random stream `stream` away
+    /// from the exits the player comes in through.
+    fn spawn_enemies_in_room(&mut self, stream: u64, min_amount: i32) {
+        let mut rng = self.stream_rng(stream);
+        let exit_distances = exit_distances(&self.wall_grid);
+        let reachable: Vec<(Vector2i, u32)> = walkable_cells(&self.wall_grid)
+            .into_iter()
+            .filter_map(|cell| Some((cell, (*exit_distances.get(cell))?)))
+            .collect();
+        // Cramped rooms without enough space fall back to the cells farthest from the exits
+        let min_distance = reachable
+            .iter()
+            .map(|(_, distance)| *distance)
+            .max()
+            .unwrap_or_default()
+            .min(Self::MIN_SPAWN_EXIT_DISTANCE);
+        let spawn_cells: Vec<Vector2i> = reachable
+            .into_iter()
+            .filter(|(_, distance)| *distance >= min_distance)
+            .map(|(cell, _)| cell)
+            .collect();
+        if spawn_cells.is_empty() {
+            return;
+        }
+        let amount_new_enemies = rng.randi_range(min_amount, 6);
+        for _ in 0..amount_new_enemies {
+            let enemy_selection = rng.randi_range(0, (self.enemy_scenes.len() - 1) as i32);
+            let enemy_scene = self
+                .enemy_scenes
+                .get(enemy_selection as usize)
+                .expect("Could not retrieve enemy scene!")
+                .clone();
+            let gun_seed = ((rng.randi() as u64) << 32) | rng.randi() as u64;
+            let cell = spawn_cells[rng.randi_range(0, spawn_cells.len() as i32 - 1) as usize];
+            let position = self.cell_center(cell);
+            self.add_enemy(&enemy_scene, position, Some(gun_seed));
+        }
+    }
+
+    fn npcs_node(&self) -> Gd<Node2D> {
+        self.base()
+            .find_child("NPCS")
+            .expect("Could not find npcs node")
+            .cast()
+    }
+
+    fn add_enemy(
+        &mut self,
+        scene: &Gd<PackedScene>,
+        position: Vector2,
+        gun_seed: Option<u64>,
+    ) -> Gd<Enemy> {
+        let mut new_enemy: Gd<Enemy> = scene
+            .instantiate()
+            .expect("Could not instantiate enemy scene!")
+            .cast();
+        if let Some(gun_seed) = gun_seed {
+            new_enemy.bind_mut().set_gun_seed(gun_seed);
+        }
+        new_enemy
+            .signals()
+            .died()
+            .connect_obj(&*self, Self::on_enemy_died);
+        self.npcs_node().add_child(&new_enemy);
+        new_enemy.set_position(position);
+        new_enemy
+    }
+
+    /// Captures the state of the room so it can be freed and restored later.
+    pub fn save_state(&self) -> RoomState {
+        let mut enemies = Vec::new();
+        let mut drops = Vec::new();
+        for child in self.npcs_node().get_children().iter_shared() {
+            if child.is_queued_for_deletion() {
+                continue;
             }
-            Some(layout) => {
-                let mut rng = RandomNumberGenerator::new_gd();
-                let current_room_position = self.base_mut().get_global_position();
-                godot_print!("Generating adjacent rooms to {:?}", current_room_position);
-                if layout.exit_right {
-                    let new_room = self.room_scene.instantiate();
-                    let mut new_room_node: Gd<Room> =
-                        new_room.expect("Could not be instantiated!").cast();
-                    let new_room_position = Vector2 {
-                        x: current_room_position.x + 576.0,
-                        y: current_room_position.y,
-                    };
-                    if self.get_room_at_position(new_room_position).is_some() {
-                        new_room_node.queue_free();
-                    } else {
-                        new_room_node.set_position(new_room_position);
-                        let new_layout = Option::from(RoomLayout {
-                            exit_top: rng.randi() % 2 == 0,
-                            exit_left: true,
-                            exit_bottom: rng.randi() % 2 == 0,
-                            exit_right: rng.randi() % 2 == 0,
-                        });
-                        new_room_node
-                            .bind_mut()
-                            .generate(rng.randi(), &new_layout.clone().unwrap());
-                        new_room_node.bind_mut().room_layout = new_layout;
-                        new_room_node.bind_mut().spawn_enemies_in_room();
-                        self.base_mut()
-                            .get_parent()
-                            .expect("Could not get parent!")
-                            .add_child(&new_room_node);
-                        godot_print!(
-                            "Generated new room at {:?}",
-                            new_room_node.get_global_position()
-                        );
-                    }
-                }
-                if layout.exit_left {
-                    let new_room = self.room_scene.instantiate();
-                    let mut new_room_node: Gd<Room> =
-                        new_room.expect("Could not be instantiated!").cast();
-                    let new_room_position = Vector2 {
-                        x: current_room_position.x - 576.0,
-                        y: current_room_position.y,
-                    };
-                    if self.get_room_at_position(new_room_position).is_some() {
-                        new_room_node.queue_free();
-                    } else {
-                        new_room_node.set_position(new_room_position);
-                        let new_layout = Option::from(RoomLayout {
-                            exit_top: rng.randi() % 2 == 0,
-                            exit_left: rng.randi() % 2 == 0,
-                            exit_bottom: rng.randi() % 2 == 0,
-                            exit_right: true,
-                        });
-                        new_room_node
-                            .bind_mut()
-                            .generate(rng.randi(), &new_layout.clone().unwrap());
-                        new_room_node.bind_mut().room_layout = new_layout;
-                        new_room_node.bind_mut().spawn_enemies_in_room();
-                        self.base_mut()
-                            .get_parent()
-                            .expect("Could not get parent!")
-                            .add_child(&new_room_node);
-                        godot_print!(
-                            "Generated new room at {:?}",
-                            new_room_node.get_global_position()
-                        );
-                    }
-                }
-                if layout.exit_bottom {
-                    let new_room = self.room_scene.instantiate();
-                    let mut new_room_node: Gd<Room> =
-                        new_room.expect("Could not be instantiated!").cast();
-                    let new_room_position = Vector2 {
-                        x: current_room_position.x,
-                        y: current_room_position.y + 352.0,
-                    };
-                    if self.get_room_at_position(new_room_position).is_some() {
-                        new_room_node.queue_free();
-                    } else {
-                        new_room_node.set_position(new_room_position);
-                        let new_layout = Option::from(RoomLayout {
-                            exit_top: true,
-                            exit_left: rng.randi() % 2 == 0,
-                            exit_bottom: rng.randi() % 2 == 0,
-                            exit_right: rng.randi() % 2 == 0,
+            let scene_path = child.get_scene_file_path().to_string();
+            match child.try_cast::<Enemy>() {
+                Ok(enemy) => enemies.push(EnemyState {
+                    scene_path,
+                    position: enemy.get_position(),
+                    health: enemy.bind().get_health(),
+                    gun_seed: enemy.bind().gun_seed(),
+                }),
+                Err(child) => {
+                    if let Ok(drop) = child.try_cast::<EnemyDrop>() {
+                        drops.push(DropState {
+                            scene_path,
+                            position: drop.get_position(),
                         });
-                        new_room_node
-                            .bind_mut()
-                            .generate(rng.randi(), &new_layout.clone().unwrap());
-                        new_room_node.bind_mut().room_layout = new_layout;
-                        new_room_node.bind_mut().spawn_enemies_in_room();
-                        self.base_mut()
-                            .get_parent()
-                            .expect("Could not get parent!")
-                            .add_child(&new_room_node);
-                        godot_print!(
-                            "Generated new room at {:?}",
-                            new_room_node.get_global_position()
-                        );
                     }
                 }
-                if layout.exit_top {
-                    let new_room = self.room_scene.instantiate();
-                    let mut new_room_node: Gd<Room> =
-                        new_room.expect("Could not be instantiated!").cast();
-                    let new_room_position = Vector2 {
-                        x: current_room_position.x,
-                        y: current_room_position.y - 352.0,
-                    };
-                    if self.get_room_at_position(new_room_position).is_some() {
-                        new_room_node.queue_free();
-                    } else {
-                        new_room_node.set_position(new_room_position);
-                        let new_layout = Option::from(RoomLayout {
-                            exit_top: rng.randi() % 2 == 0,
-                            exit_left: rng.randi() % 2 == 0,
-                            exit_bottom: true,
-                            exit_right: rng.randi() % 2 == 0,
-                        });
-                        new_room_node
-                            .bind_mut()
-                            .generate(rng.randi(), &new_layout.clone().unwrap());
-                        new_room_node.bind_mut().room_layout = new_layout;
-                        new_room_node.bind_mut().spawn_enemies_in_room();
-                        self.base_mut()
-                            .get_parent()
-                            .expect("Could not get parent!")
-                            .add_child(&new_room_node);
-                        godot_print!(
-                            "Generated new room at {:?}",
-                            new_room_node.get_global_position()
-                        );
-                    }
-                }
-                self.adjacent_rooms_generated = true;
             }
         }
+
+        RoomState {
+            seed: self.seed,
+            room_type: self.room_type,
+            layout: self.room_layout.clone().unwrap_or_default(),
+            cleared: self.cleared || (enemies.is_empty() && self.waves_left == 0),
+            waves_left: self.waves_left,
+            destroyed_walls: self.destroyed_walls.clone(),
+            enemies,
+            drops,
+        }
     }
 
-    fn spawn_enemies_in_room(&mut self) {
-        let mut rng = RandomNumberGenerator::new_gd();
-        let amount_new_enemies = rng.randi_range(0, 6);
-        for i in 0..amount_new_enemies {
-            let enemy_selection = rng.randi_range(0, (self.enemy_scenes.len() - 1) as i32);
-            let mut new_enemy: Gd<Enemy> = self
-                .enemy_scenes
-                .get(enemy_selection as usize)
-                .expect("Could not retrieve enemy scene!")
+    /// Regenerates the room from a saved state and brings back its enemies and drops.
+    fn restore_state(&mut self, state: RoomState) {
+        self.seed = state.seed;
+        self.room_type = state.room_type;
+        self.cleared = state.cleared;
+        self.waves_left = state.waves_left;
+        self.generate(&state.layout);
+        self.room_layout = Some(state.layout);
+        if !state.destroyed_walls.is_empty() {
+            for cell in &state.destroyed_walls {
+                self.clear_wall(*cell);
+            }
+            self.redraw_walls(&state.destroyed_walls);
+        }
+
+        for enemy_state in state.enemies {
+            let scene = load::<PackedScene>(enemy_state.scene_path.as_str());
+            let mut enemy = self.add_enemy(&scene, enemy_state.position, enemy_state.gun_seed);
+            enemy.bind_mut().set_health(enemy_state.health);
+        }
+        for drop_state in state.drops {
+            let mut drop: Gd<Node2D> = load::<PackedScene>(drop_state.scene_path.as_str())
                 .instantiate()
-                .expect("Could not instantiate enemy scene!")
+                .expect("Could not instantiate drop scene!")
                 .cast();
-            let mut enemies_node: Gd<Node2D> = self
-                .base_mut()
-                .find_child("NPCS")
-                .expect("Could not find npcs node")
-                .cast();
-            enemies_node.add_child(&new_enemy);
-            new_enemy.set_position(Vector2 {
-                x: rng.randi_range(0, self.width * 32) as real,
-                y: rng.randi_range(0, self.height * 32) as real,
-            });
+            self.npcs_node().add_child(&drop);
+            drop.set_position(drop_state.position);
+        }
+    }
+
+    fn live_enemies(&self) -> usize {
+        self.npcs_node()
+            .get_children()
+            .iter_shared()
+            .filter(|child| !child.is_queued_for_deletion() && child.is_class("Enemy"))
+            .count()
+    }
+
+    /// Starts the encounter of the room once the player at the global position `player_pos`
+    /// has stepped inside, past its exits.
+    pub fn update_encounter(&mut self, player_pos: Vector2) {
+        if self.cleared || self.encounter_active {
+            return;
+        }
+        let Some(cell) = self.cell_at(player_pos) else {
+            return;
+        };
+        if cell.x <= 0 || cell.y <= 0 || cell.x >= self.width - 1 || cell.y >= self.height - 1 {
+            return;
+        }
+
+        if self.live_enemies() == 0 && self.waves_left == 0 {
+            self.cleared = true;
+            return;
+        }
+        self.encounter_active = true;
+        self.lock_exits();
+        if self.live_enemies() == 0 {
+            self.spawn_next_wave();
+        }
+    }
+
+    fn on_enemy_died(&mut self) {
+        if !self.encounter_active || self.live_enemies() > 0 {
+            return;
+        }
+        if self.waves_left > 0 {
+            self.spawn_next_wave();
+        } else {
+            self.finish_encounter();
+        }
+    }
+
+    fn spawn_next_wave(&mut self) {
+        self.waves_left = self.waves_left.saturating_sub(1);
+        let stream = mix_seed(Self::WAVE_SEED_STREAM, self.waves_left as u64);
+        self.spawn_enemies_in_room(stream, 1);
+    }
+
+    fn finish_encounter(&mut self) {
+        self.encounter_active = false;
+        self.cleared = true;
+        self.unlock_exits();
+        let Some(reward) = self.clear_reward.clone() else {
+            return;
+        };
+        if let Some(cell) = self.nearest_walkable_cell(self.center_cell()) {
+            self.add_item(&reward, cell);
+        }
+    }
+
+    /// Walls off every exit of the room until the encounter is over.
+    fn lock_exits(&mut self) {
+        self.locked_exits = exit_cells(&self.wall_grid);
+        for cell in &self.locked_exits {
+            self.wall_grid.set(*cell, WallTile::Wall);
         }
+        let cells = self.locked_exits.clone();
+        self.redraw_walls(&cells);
+    }
+
+    fn unlock_exits(&mut self) {
+        let cells = std::mem::take(&mut self.locked_exits);
+        for cell in &cells {
+            self.wall_grid.set(*cell, WallTile::Clear);
+        }
+        self.redraw_walls(&cells);
     }
 
     pub fn en_disable_enemies_in_room(&mut self, state: bool) {
@@ -722,10 +1090,11 @@ impl Room {
             };
             let enemy_pos = enemy.get_global_position();
             let room_pos = self.base_mut().get_global_position();
+            let room_size = self.pixel_size();
             if enemy_pos.x > room_pos.x
-                && enemy_pos.x < room_pos.x + (self.width as real * 32.0)
+                && enemy_pos.x < room_pos.x + room_size.x
                 && enemy_pos.y > room_pos.y
-                && enemy_pos.y < room_pos.y + (self.height as real * 32.0)
+                && enemy_pos.y < room_pos.y + room_size.y
             {
                 if state {
                     enemy.set_process_mode(ProcessMode::INHERIT)
@@ -735,20 +1104,4 @@ impl Room {
             }
         }
     }
-
-    fn get_room_at_position(&mut self, pos: Vector2) -> Option<Gd<Room>> {
-        let rooms = self
-            .base_mut()
-            .get_tree()
-            .expect("Could not retrieve tree")
-            .get_nodes_in_group("room");
-        for i in 0..rooms.len() {
-            let mut room: Gd<Room> = rooms.get(i).expect("Could not retrieve room!").cast();
-            if pos.x == room.get_global_position().x && pos.y == room.get_global_position().y {
-                godot_print!("Duplicated room! {:?}", pos);
-                return Option::from(room);
-            }
-        }
-        None
-    }
 }
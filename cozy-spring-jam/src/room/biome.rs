@@ -0,0 +1,118 @@
+use crate::utils::{FractalType, Noise, NoiseType, mix_seed};
+use godot::builtin::{Vector2, Vector2i};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Meadow,
+    #[default]
+    Forest,
+    Marsh,
+    Rocky,
+}
+
+/// Thresholds deciding where the biome specific tiles show up in a room.
+///
+/// Water and mud replace dirt where the moisture noise is high, flowers replace grass and rocks
+/// grow where the special noise is low. Thorns replace tall grass where the moisture is low.
+/// `f32::INFINITY` disables a tile.
+#[derive(Debug, Clone, Copy)]
+pub struct BiomeRules {
+    pub water_cutoff: f32,
+    pub mud_cutoff: f32,
+    pub flowers_cutoff: f32,
+    pub rock_cutoff: f32,
+    pub thorns_cutoff: f32,
+}
+
+impl Biome {
+    pub fn rules(self) -> BiomeRules {
+        match self {
+            Biome::Meadow => BiomeRules {
+                water_cutoff: 0.5,
+                mud_cutoff: f32::INFINITY,
+                flowers_cutoff: -0.2,
+                rock_cutoff: f32::INFINITY,
+                thorns_cutoff: f32::INFINITY,
+            },
+            Biome::Forest => BiomeRules {
+                water_cutoff: f32::INFINITY,
+                mud_cutoff: 0.4,
+                flowers_cutoff: 0.5,
+                rock_cutoff: 2.5,
+                thorns_cutoff: 0.3,
+            },
+            Biome::Marsh => BiomeRules {
+                water_cutoff: 0.1,
+                mud_cutoff: -0.2,
+                flowers_cutoff: f32::INFINITY,
+                rock_cutoff: f32::INFINITY,
+                thorns_cutoff: f32::INFINITY,
+            },
+            Biome::Rocky => BiomeRules {
+                water_cutoff: f32::INFINITY,
+                mud_cutoff: 0.5,
+                flowers_cutoff: f32::INFINITY,
+                rock_cutoff: 0.8,
+                thorns_cutoff: 0.2,
+            },
+        }
+    }
+}
+
+/// World-scale noise deciding which biome covers which part of the world.
+///
+/// Positions are in rooms, so `(2.5, 0.5)` is the center of the room at `(2, 0)`. As the noise
+/// is continuous, biomes change in the middle of rooms and neighbouring rooms always agree on
+/// the biome along their shared edge.
+#[derive(Debug, Clone)]
+pub struct BiomeMap {
+    moisture: Noise,
+    elevation: Noise,
+}
+
+impl BiomeMap {
+    const MOISTURE_SEED_STREAM: u64 = 20;
+    const ELEVATION_SEED_STREAM: u64 = 21;
+
+    pub fn new(run_seed: u64) -> Self {
+        let noise = |stream| {
+            Noise::new(NoiseType::Perlin, mix_seed(run_seed, stream) as i32)
+                .with_frequency(0.25)
+                .with_fractal_type(FractalType::Fbm)
+                .with_fractal_octaves(2)
+        };
+        Self {
+            moisture: noise(Self::MOISTURE_SEED_STREAM),
+            elevation: noise(Self::ELEVATION_SEED_STREAM),
+        }
+    }
+
+    pub fn biome_at(&self, pos: Vector2) -> Biome {
+        let moisture = self.moisture.get_noise_2d(pos.x, pos.y);
+        let elevation = self.elevation.get_noise_2d(pos.x, pos.y);
+        if moisture > 0.25 {
+            Biome::Marsh
+        } else if elevation > 0.25 {
+            Biome::Rocky
+        } else if moisture < -0.2 {
+            Biome::Meadow
+        } else {
+            Biome::Forest
+        }
+    }
+
+    /// Returns the biome at the center of `cell` in a `width` by `height` room at the grid
+    /// coordinate `coord`.
+    pub fn biome_at_cell(
+        &self,
+        coord: Vector2i,
+        width: usize,
+        height: usize,
+        cell: Vector2i,
+    ) -> Biome {
+        self.biome_at(Vector2::new(
+            coord.x as f32 + (cell.x as f32 + 0.5) / width as f32,
+            coord.y as f32 + (cell.y as f32 + 0.5) / height as f32,
+        ))
+    }
+}
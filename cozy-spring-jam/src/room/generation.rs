@@ -0,0 +1,662 @@
+use crate::room::biome::{Biome, BiomeMap};
+use crate::room::prefab::Prefab;
+use crate::room::validation::connect_regions;
+use crate::utils::{FractalType, Grid, Noise, NoiseType};
+use godot::builtin::{Vector2, Vector2i};
+
+#[derive(Debug, Clone)]
+pub struct RoomGenParams {
+    pub growth_falloff: f32,
+    pub edge_growth: f32,
+    pub center_growth: f32,
+    pub growth_noise_amplitude: f32,
+    pub growth_noise_frequency: f32,
+    pub grow_noise_octaves: i32,
+    pub growth_noise_bias: f32,
+    pub special_noise_frequency: f32,
+    pub special_noise_amplitude: f32,
+    pub noise_fractal_gain: f32,
+    pub tree_growth_cutoff: f32,
+    pub lone_tree_growth_cutoff: f32,
+    pub lone_tree_special_cutoff: f32,
+    pub grass_growth_cutoff: f32,
+    pub tall_grass_growth_cutoff: f32,
+    pub exit_size: u32,
+    pub max_carved_cells: usize,
+    pub max_attempts: u32,
+}
+
+impl Default for RoomGenParams {
+    fn default() -> Self {
+        Self {
+            edge_growth: 1.5,
+            center_growth: -1.0,
+            growth_falloff: 0.6,
+            growth_noise_amplitude: 4.0,
+            growth_noise_frequency: 0.1,
+            growth_noise_bias: -1.7,
+            special_noise_frequency: 0.5,
+            special_noise_amplitude: 3.0,
+            grow_noise_octaves: 3,
+            noise_fractal_gain: 0.6,
+            tree_growth_cutoff: 0.6,
+            lone_tree_growth_cutoff: 0.4,
+            lone_tree_special_cutoff: 0.4,
+            grass_growth_cutoff: 0.0,
+            tall_grass_growth_cutoff: 0.2,
+            exit_size: 2,
+            max_carved_cells: 12,
+            max_attempts: 4,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RoomType {
+    Start,
+    #[default]
+    Normal,
+    Treasure,
+    Shop,
+    Boss,
+    Safe,
+}
+
+impl RoomType {
+    /// Whether the room starts out with enemies that have to be defeated.
+    pub fn has_combat(self) -> bool {
+        matches!(self, Self::Normal | Self::Boss)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub const ALL: &[Self] = &[Self::Top, Self::Bottom, Self::Left, Self::Right];
+
+    /// Offset to the neighbouring room in this direction, in room grid coordinates.
+    pub fn offset(self) -> Vector2i {
+        match self {
+            Self::Top => Vector2i::UP,
+            Self::Bottom => Vector2i::DOWN,
+            Self::Left => Vector2i::LEFT,
+            Self::Right => Vector2i::RIGHT,
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::Top => Self::Bottom,
+            Self::Bottom => Self::Top,
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+        }
+    }
+
+    pub fn is_vertical(self) -> bool {
+        matches!(self, Self::Top | Self::Bottom)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoomLayout {
+    pub exit_top: bool,
+    pub exit_bottom: bool,
+    pub exit_left: bool,
+    pub exit_right: bool,
+}
+
+impl RoomLayout {
+    pub fn has_exit(&self, dir: Direction) -> bool {
+        match dir {
+            Direction::Top => self.exit_top,
+            Direction::Bottom => self.exit_bottom,
+            Direction::Left => self.exit_left,
+            Direction::Right => self.exit_right,
+        }
+    }
+
+    pub fn set_exit(&mut self, dir: Direction, open: bool) {
+        match dir {
+            Direction::Top => self.exit_top = open,
+            Direction::Bottom => self.exit_bottom = open,
+            Direction::Left => self.exit_left = open,
+            Direction::Right => self.exit_right = open,
+        }
+    }
+
+    pub fn exits(&self) -> impl Iterator<Item = Direction> + '_ {
+        Direction::ALL
+            .iter()
+            .copied()
+            .filter(|dir| self.has_exit(*dir))
+    }
+}
+
+impl RoomGenParams {
+    /// Returns the parameters adjusted to the needs of the given room type.
+    pub fn for_room_type(&self, room_type: RoomType) -> Self {
+        let mut params = self.clone();
+        match room_type {
+            RoomType::Normal => (),
+            RoomType::Start | RoomType::Safe | RoomType::Shop | RoomType::Treasure => {
+                // Calm clearings with fewer trees in the middle
+                params.center_growth -= 0.5;
+                params.growth_noise_bias -= 0.5;
+                params.lone_tree_special_cutoff += 0.3;
+            }
+            RoomType::Boss => {
+                // Open arenas leave room to dodge bullets
+                params.growth_falloff += 0.4;
+                params.tree_growth_cutoff += 0.2;
+                params.lone_tree_growth_cutoff += 0.4;
+            }
+        }
+        params
+    }
+}
+
+struct SpecialField<'a> {
+    params: &'a RoomGenParams,
+    noise: Noise,
+}
+
+impl<'a> SpecialField<'a> {
+    fn new(seed: u32, params: &'a RoomGenParams) -> Self {
+        let noise = Noise::new(NoiseType::Value, seed as i32)
+            .with_frequency(params.special_noise_frequency);
+        Self { params, noise }
+    }
+
+    fn get_special_factor(&self, x: usize, y: usize) -> f32 {
+        let x = x as f32;
+        let y = y as f32;
+
+        self.params.special_noise_amplitude * self.noise.get_noise_2d(x, y)
+    }
+}
+
+struct BiomeField<'a> {
+    biomes: Option<&'a BiomeMap>,
+    coord: Vector2i,
+    width: usize,
+    height: usize,
+    moisture: Noise,
+}
+
+impl<'a> BiomeField<'a> {
+    fn new(
+        width: usize,
+        height: usize,
+        seed: u32,
+        biomes: Option<&'a BiomeMap>,
+        coord: Vector2i,
+    ) -> Self {
+        let moisture = Noise::new(NoiseType::Perlin, seed.wrapping_add(1) as i32)
+            .with_frequency(0.15)
+            .with_fractal_octaves(2);
+        Self {
+            biomes,
+            coord,
+            width,
+            height,
+            moisture,
+        }
+    }
+
+    fn get_biome(&self, x: usize, y: usize) -> Biome {
+        let Some(biomes) = self.biomes else {
+            return Biome::default();
+        };
+        let cell = Vector2i::new(x as i32, y as i32);
+        biomes.biome_at_cell(self.coord, self.width, self.height, cell)
+    }
+
+    fn get_moisture(&self, x: usize, y: usize) -> f32 {
+        self.moisture.get_noise_2d(x as f32, y as f32)
+    }
+}
+
+struct GrowthField<'a> {
+    params: &'a RoomGenParams,
+    layout: &'a RoomLayout,
+    width: f32,
+    height: f32,
+    noise: Noise,
+}
+
+impl<'a> GrowthField<'a> {
+    fn new(
+        width: usize,
+        height: usize,
+        seed: u32,
+        params: &'a RoomGenParams,
+        layout: &'a RoomLayout,
+    ) -> Self {
+        let noise = Noise::new(NoiseType::Perlin, seed as i32)
+            .with_frequency(params.growth_noise_frequency)
+            .with_fractal_octaves(params.grow_noise_octaves)
+            .with_fractal_type(FractalType::Ridged)
+            .with_fractal_gain(params.noise_fractal_gain);
+        Self {
+            params,
+            layout,
+            width: width as f32 - 1.0,
+            height: height as f32 - 1.0,
+            noise,
+        }
+    }
+
+    fn get_edge_dist(&self, pos: Vector2) -> f32 {
+        let half_width = self.width / 2.0;
+        let half_height = self.height / 2.0;
+
+        let x_dist = (half_width - f32::abs(pos.x - half_width)).max(0.0);
+        let y_dist = (half_height - f32::abs(pos.y - half_height)).max(0.0);
+
+        f32::min(x_dist, y_dist)
+    }
+
+    fn is_on_critical_path(&self, pos_from_origin: Vector2, path: Vector2) -> bool {
+        let path_dir = path.normalized();
+        let dist_along_path = pos_from_origin.dot(path_dir);
+        if dist_along_path.is_sign_negative() {
+            return false;
+        }
+
+        let path_proportion = dist_along_path / path.length();
+        let dist_to_center_line = (pos_from_origin - dist_along_path * path_dir).length();
+
+        dist_to_center_line <= path_proportion * self.params.exit_size as f32
+    }
+
+    fn is_in_critical_section(&self, pos: Vector2) -> bool {
+        let origin = Vector2::new(self.width, self.height) / 2.0;
+        let pos_from_origin = pos - origin;
+
+        self.layout.exits().any(|dir| {
+            let offset = dir.offset();
+            let path = Vector2::new(offset.x as f32, offset.y as f32) * origin;
+            self.is_on_critical_path(pos_from_origin, path)
+        })
+    }
+
+    fn get_const_component(&self, pos: Vector2) -> f32 {
+        let edge_dist = self.get_edge_dist(pos);
+
+        (self.params.edge_growth * (1.0 - edge_dist * self.params.growth_falloff))
+            .max(self.params.center_growth)
+    }
+
+    fn get_noise_component(&self, pos: Vector2) -> f32 {
+        self.params.growth_noise_amplitude * self.noise.get_noise_2d(pos.x, pos.y)
+            + self.params.growth_noise_bias
+    }
+
+    fn compute_growth_factor(&self, pos: Vector2) -> f32 {
+        if self.is_in_critical_section(pos) {
+            return f32::NEG_INFINITY;
+        }
+
+        (self.get_const_component(pos) + self.get_noise_component(pos)).clamp(-1.0, 1.0)
+    }
+
+    fn get_growth_factor(&self, pos: Vector2i) -> f32 {
+        self.compute_growth_factor(Vector2::new(pos.x as f32, pos.y as f32))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FloorTile {
+    #[default]
+    None,
+    Dirt,
+    Grass,
+    TallGrass,
+    Water,
+    Flowers,
+    Mud,
+    Thorns,
+}
+
+impl FloorTile {
+    /// Multiplier on the speed of anything moving over the tile.
+    pub fn speed_factor(self) -> f32 {
+        match self {
+            Self::Water => 0.6,
+            Self::Mud => 0.75,
+            _ => 1.0,
+        }
+    }
+
+    /// Damage dealt every second to anything standing on the tile.
+    pub fn damage_per_second(self) -> f32 {
+        match self {
+            Self::Thorns => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WallTile {
+    #[default]
+    Clear,
+    Wall,
+    LoneTree,
+    Rock,
+}
+
+impl WallTile {
+    /// Hit points of the tile, or `None` if bullets can't destroy it.
+    pub fn max_health(self) -> Option<f32> {
+        match self {
+            Self::Clear => None,
+            Self::Wall => Some(6.0),
+            Self::LoneTree => Some(3.0),
+            Self::Rock => Some(10.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GeneratedRoom {
+    pub floor: Grid<FloorTile>,
+    pub walls: Grid<WallTile>,
+    /// The biome at the center of the room, which decides the terrain sets of its layers.
+    pub biome: Biome,
+}
+
+/// Generates the tiles of a room from its seed and layout, without touching any Godot objects.
+pub struct RoomGenerator<'a> {
+    width: i32,
+    height: i32,
+    params: &'a RoomGenParams,
+    biomes: Option<&'a BiomeMap>,
+    coord: Vector2i,
+    prefab: Option<&'a Prefab>,
+}
+
+impl<'a> RoomGenerator<'a> {
+    pub fn new(width: i32, height: i32, params: &'a RoomGenParams) -> Self {
+        Self {
+            width,
+            height,
+            params,
+            biomes: None,
+            coord: Vector2i::ZERO,
+            prefab: None,
+        }
+    }
+
+    /// Picks the biome of every tile from `biomes`, for a room at the grid coordinate `coord`.
+    /// Without it, the whole room is a forest.
+    pub fn with_biomes(mut self, biomes: &'a BiomeMap, coord: Vector2i) -> Self {
+        self.biomes = Some(biomes);
+        self.coord = coord;
+        self
+    }
+
+    /// Stamps `prefab` into the center of every generated room.
+    pub fn with_prefab(mut self, prefab: &'a Prefab) -> Self {
+        self.prefab = Some(prefab);
+        self
+    }
+
+    /// Generates a room where all exits and all walkable floor are connected.
+    ///
+    /// Rooms that need more than `max_carved_cells` carved to be connected are rerolled with a
+    /// derived seed, keeping the attempt that needed the least carving.
+    pub fn generate(&self, seed: u32, layout: &RoomLayout) -> GeneratedRoom {
+        let mut room = self.generate_unchecked(seed, layout);
+        let mut carved = connect_regions(&mut room.walls);
+
+        for attempt in 1..self.params.max_attempts {
+            if carved <= self.params.max_carved_cells {
+                break;
+            }
+            let attempt_seed = seed ^ attempt.wrapping_mul(0x9e37_79b9);
+            let mut candidate = self.generate_unchecked(attempt_seed, layout);
+            let candidate_carved = connect_regions(&mut candidate.walls);
+            if candidate_carved < carved {
+                room = candidate;
+                carved = candidate_carved;
+            }
+        }
+        room
+    }
+
+    fn generate_unchecked(&self, seed: u32, layout: &RoomLayout) -> GeneratedRoom {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let growth_field = GrowthField::new(width, height, seed, self.params, layout);
+        let special = SpecialField::new(seed, self.params);
+        let biome = BiomeField::new(width, height, seed, self.biomes, self.coord);
+
+        let growth = Grid::from_fn(width, height, |pos| growth_field.get_growth_factor(pos));
+        let floor = growth.map(|pos, growth_factor| self.floor_tile(pos, *growth_factor, &biome));
+        let mut walls = growth
+            .map(|pos, growth_factor| self.special_tile(pos, *growth_factor, &special, &biome));
+        self.place_walls(&mut walls, &growth, layout);
+
+        let mut room = GeneratedRoom {
+            floor,
+            walls,
+            biome: biome.get_biome(width / 2, height / 2),
+        };
+        if let Some(prefab) = self.prefab {
+            room.stamp(&prefab.room, prefab.centered_origin(width, height));
+        }
+        room
+    }
+
+    fn floor_tile(&self, pos: Vector2i, growth_factor: f32, biome: &BiomeField) -> FloorTile {
+        let (x, y) = (pos.x as usize, pos.y as usize);
+        let moisture = biome.get_moisture(x, y);
+        let rules = biome.get_biome(x, y).rules();
+
+        if growth_factor >= self.params.tall_grass_growth_cutoff {
+            if -moisture >= rules.thorns_cutoff {
+                FloorTile::Thorns
+            } else {
+                FloorTile::TallGrass
+            }
+        } else if growth_factor >= self.params.grass_growth_cutoff {
+            if moisture >= rules.flowers_cutoff {
+                FloorTile::Flowers
+            } else {
+                FloorTile::Grass
+            }
+        } else if moisture >= rules.water_cutoff {
+            FloorTile::Water
+        } else if moisture >= rules.mud_cutoff {
+            FloorTile::Mud
+        } else {
+            FloorTile::Dirt
+        }
+    }
+
+    fn special_tile(
+        &self,
+        pos: Vector2i,
+        growth_factor: f32,
+        special: &SpecialField,
+        biome: &BiomeField,
+    ) -> WallTile {
+        let (x, y) = (pos.x as usize, pos.y as usize);
+        let special_factor = special.get_special_factor(x, y);
+        let rules = biome.get_biome(x, y).rules();
+
+        if special_factor >= self.params.lone_tree_special_cutoff
+            && growth_factor >= self.params.lone_tree_growth_cutoff
+        {
+            WallTile::LoneTree
+        } else if -special_factor >= rules.rock_cutoff && growth_factor.is_finite() {
+            WallTile::Rock
+        } else {
+            WallTile::Clear
+        }
+    }
+
+    fn place_walls(&self, grid: &mut Grid<WallTile>, growth: &Grid<f32>, layout: &RoomLayout) {
+        for (pos, tile) in grid.iter_mut() {
+            if self.should_have_edge_wall(pos.x, pos.y, layout)
+                || *growth.get(pos) >= self.params.tree_growth_cutoff
+            {
+                *tile = WallTile::Wall;
+            }
+        }
+    }
+
+    fn should_have_edge_wall(&self, x: i32, y: i32, layout: &RoomLayout) -> bool {
+        let center_dist = Vector2i::new(x - self.width / 2, y - self.height / 2);
+        let max_dist = self.params.exit_size as i32 / 2;
+
+        for dir in layout.exits() {
+            let offset = dir.offset();
+            let (across, along, along_sign) = if dir.is_vertical() {
+                (center_dist.x, center_dist.y, offset.y)
+            } else {
+                (center_dist.y, center_dist.x, offset.x)
+            };
+            if across.abs() <= max_dist && along.signum() == along_sign {
+                return false;
+            }
+        }
+
+        let is_edge = y == 0 || y == self.height - 1 || x == 0 || x == self.width - 1;
+        is_edge
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::room::validation::{exit_cells, walking_distances};
+
+    /// Compares `room` against the checked-in snapshot `name`. Setting `UPDATE_SNAPSHOTS`
+    /// rewrites the snapshot instead.
+    fn assert_snapshot(name: &str, room: &GeneratedRoom) {
+        let path = format!(
+            "{}/src/room/snapshots/{name}.txt",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let actual = room.to_ascii();
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(&path, &actual).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("missing snapshot {path}: {err}"));
+        assert_eq!(actual, expected, "room differs from snapshot {name}");
+    }
+
+    fn all_exits() -> RoomLayout {
+        RoomLayout {
+            exit_top: true,
+            exit_bottom: true,
+            exit_left: true,
+            exit_right: true,
+        }
+    }
+
+    #[test]
+    fn same_seed_generates_same_room() {
+        let params = RoomGenParams::default();
+        let generator = RoomGenerator::new(18, 11, &params);
+        let first = generator.generate(42, &all_exits());
+        let second = generator.generate(42, &all_exits());
+        assert_eq!(first.to_ascii(), second.to_ascii());
+    }
+
+    #[test]
+    fn snapshot_default_params() {
+        let params = RoomGenParams::default();
+        let generator = RoomGenerator::new(18, 11, &params);
+        assert_snapshot("default_seed_1", &generator.generate(1, &all_exits()));
+        let layout = RoomLayout {
+            exit_top: true,
+            exit_bottom: false,
+            exit_left: false,
+            exit_right: true,
+        };
+        assert_snapshot("default_seed_7_corner", &generator.generate(7, &layout));
+    }
+
+    #[test]
+    fn snapshot_boss_room() {
+        let params = RoomGenParams::default().for_room_type(RoomType::Boss);
+        let generator = RoomGenerator::new(18, 11, &params);
+        assert_snapshot("boss_seed_3", &generator.generate(3, &all_exits()));
+    }
+
+    #[test]
+    fn room_biome_is_center_cell_biome() {
+        let params = RoomGenParams::default();
+        let biomes = BiomeMap::new(99);
+        for x in -5..5 {
+            let coord = Vector2i::new(x, 2 * x);
+            let room = RoomGenerator::new(18, 11, &params)
+                .with_biomes(&biomes, coord)
+                .generate(1, &all_exits());
+            let center = biomes.biome_at_cell(coord, 18, 11, Vector2i::new(9, 5));
+            assert_eq!(room.biome, center);
+        }
+    }
+
+    #[test]
+    fn generated_rooms_connect_every_exit_and_all_floor() {
+        let biomes = BiomeMap::new(7);
+        for room_type in [RoomType::Normal, RoomType::Boss] {
+            let params = RoomGenParams::default().for_room_type(room_type);
+            let generator =
+                RoomGenerator::new(18, 11, &params).with_biomes(&biomes, Vector2i::new(3, 1));
+            for exits in 1..16 {
+                let layout = RoomLayout {
+                    exit_top: exits & 1 != 0,
+                    exit_bottom: exits & 2 != 0,
+                    exit_left: exits & 4 != 0,
+                    exit_right: exits & 8 != 0,
+                };
+                for seed in 0..20 {
+                    let room = generator.generate(seed, &layout);
+                    let exit_cells = exit_cells(&room.walls);
+                    for dir in layout.exits() {
+                        let on_side = |pos: &Vector2i| match dir {
+                            Direction::Top => pos.y == 0,
+                            Direction::Bottom => pos.y == 10,
+                            Direction::Left => pos.x == 0,
+                            Direction::Right => pos.x == 17,
+                        };
+                        assert!(
+                            exit_cells.iter().any(on_side),
+                            "seed {seed} lost its {dir:?} exit"
+                        );
+                    }
+                    let distances = walking_distances(&room.walls, exit_cells[0]);
+                    for (pos, tile) in room.walls.iter() {
+                        assert!(
+                            *tile != WallTile::Clear || distances.get(pos).is_some(),
+                            "seed {seed} can't reach {pos:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn snapshot_biomes() {
+        let params = RoomGenParams::default();
+        let biomes = BiomeMap::new(12345);
+        let generator =
+            RoomGenerator::new(18, 11, &params).with_biomes(&biomes, Vector2i::new(2, -8));
+        assert_snapshot("biomes_seed_9", &generator.generate(9, &all_exits()));
+    }
+}
@@ -0,0 +1,391 @@
+use crate::room::generation::{FloorTile, GeneratedRoom, WallTile};
+use crate::utils::Grid;
+use godot::builtin::Vector2i;
+use std::fmt;
+
+/// A tile that can be written to and read from the ASCII and binary map formats.
+pub trait MapTile: Sized + Default + Clone {
+    fn to_char(&self) -> char;
+
+    fn from_char(ch: char) -> Option<Self>;
+
+    fn to_byte(&self) -> u8;
+
+    fn from_byte(byte: u8) -> Option<Self>;
+}
+
+impl MapTile for FloorTile {
+    fn to_char(&self) -> char {
+        match self {
+            Self::None => '_',
+            Self::Dirt => '.',
+            Self::Grass => ',',
+            Self::TallGrass => '"',
+            Self::Water => '~',
+            Self::Flowers => '*',
+            Self::Mud => 'm',
+            Self::Thorns => '%',
+        }
+    }
+
+    fn from_char(ch: char) -> Option<Self> {
+        match ch {
+            '_' => Some(Self::None),
+            '.' => Some(Self::Dirt),
+            ',' => Some(Self::Grass),
+            '"' => Some(Self::TallGrass),
+            '~' => Some(Self::Water),
+            '*' => Some(Self::Flowers),
+            'm' => Some(Self::Mud),
+            '%' => Some(Self::Thorns),
+            _ => None,
+        }
+    }
+
+    fn to_byte(&self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Dirt => 1,
+            Self::Grass => 2,
+            Self::TallGrass => 3,
+            Self::Water => 4,
+            Self::Flowers => 5,
+            Self::Mud => 6,
+            Self::Thorns => 7,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::None),
+            1 => Some(Self::Dirt),
+            2 => Some(Self::Grass),
+            3 => Some(Self::TallGrass),
+            4 => Some(Self::Water),
+            5 => Some(Self::Flowers),
+            6 => Some(Self::Mud),
+            7 => Some(Self::Thorns),
+            _ => None,
+        }
+    }
+}
+
+impl MapTile for WallTile {
+    fn to_char(&self) -> char {
+        match self {
+            Self::Clear => '.',
+            Self::Wall => '#',
+            Self::LoneTree => 'T',
+            Self::Rock => 'o',
+        }
+    }
+
+    fn from_char(ch: char) -> Option<Self> {
+        match ch {
+            '.' => Some(Self::Clear),
+            '#' => Some(Self::Wall),
+            'T' => Some(Self::LoneTree),
+            'o' => Some(Self::Rock),
+            _ => None,
+        }
+    }
+
+    fn to_byte(&self) -> u8 {
+        match self {
+            Self::Clear => 0,
+            Self::Wall => 1,
+            Self::LoneTree => 2,
+            Self::Rock => 3,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Clear),
+            1 => Some(Self::Wall),
+            2 => Some(Self::LoneTree),
+            3 => Some(Self::Rock),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapFormatError {
+    Empty,
+    RaggedRow { row: usize, width: usize },
+    UnknownChar { ch: char, pos: Vector2i },
+    BadHeader,
+    Truncated,
+    UnknownByte { byte: u8, pos: Vector2i },
+    MissingSection,
+    SizeMismatch { walls: Vector2i, floor: Vector2i },
+}
+
+impl fmt::Display for MapFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the map is empty"),
+            Self::RaggedRow { row, width } => {
+                write!(f, "row {row} is not {width} tiles wide like the first one")
+            }
+            Self::UnknownChar { ch, pos } => write!(f, "unknown tile {ch:?} at {pos:?}"),
+            Self::BadHeader => write!(f, "the data does not start with a grid header"),
+            Self::Truncated => write!(f, "the data ends before the grid is complete"),
+            Self::UnknownByte { byte, pos } => write!(f, "unknown tile {byte} at {pos:?}"),
+            Self::MissingSection => write!(f, "a room needs a wall and a floor section"),
+            Self::SizeMismatch { walls, floor } => write!(
+                f,
+                "the walls are {}x{} tiles but the floor is {}x{}",
+                walls.x, walls.y, floor.x, floor.y
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MapFormatError {}
+
+/// Writes a grid as one line of characters per row.
+pub fn grid_to_ascii<T: MapTile>(grid: &Grid<T>) -> String {
+    (0..grid.height())
+        .map(|y| grid.row(y).iter().map(MapTile::to_char).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Reads a grid written by [`grid_to_ascii`]. Trailing line breaks are ignored, but every row
+/// has to be as wide as the first one.
+pub fn grid_from_ascii<T: MapTile>(text: &str) -> Result<Grid<T>, MapFormatError> {
+    let rows: Vec<Vec<char>> = text
+        .trim_end_matches(['\n', '\r'])
+        .lines()
+        .map(|line| line.trim_end_matches('\r').chars().collect())
+        .collect();
+    let width = rows.first().map_or(0, Vec::len);
+    if width == 0 {
+        return Err(MapFormatError::Empty);
+    }
+    if let Some(row) = rows.iter().position(|row| row.len() != width) {
+        return Err(MapFormatError::RaggedRow { row, width });
+    }
+
+    let mut grid = Grid::new(width, rows.len());
+    for (pos, tile) in grid.iter_mut() {
+        let ch = rows[pos.y as usize][pos.x as usize];
+        *tile = T::from_char(ch).ok_or(MapFormatError::UnknownChar { ch, pos })?;
+    }
+    Ok(grid)
+}
+
+const MAGIC: &[u8; 4] = b"GRID";
+const HEADER_LEN: usize = MAGIC.len() + 4;
+
+/// Writes a grid as the magic bytes `GRID`, the width and height as little endian `u16` and one
+/// byte per tile, row by row.
+///
+/// # Panics
+///
+/// If the grid is wider or taller than `u16::MAX` tiles.
+pub fn grid_to_bytes<T: MapTile>(grid: &Grid<T>) -> Vec<u8> {
+    assert!(
+        grid.width() <= u16::MAX as usize && grid.height() <= u16::MAX as usize,
+        "a {}x{} grid is too large for the binary map format",
+        grid.width(),
+        grid.height()
+    );
+    let mut bytes = Vec::with_capacity(HEADER_LEN + grid.width() * grid.height());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&(grid.width() as u16).to_le_bytes());
+    bytes.extend_from_slice(&(grid.height() as u16).to_le_bytes());
+    bytes.extend(grid.iter().map(|(_, tile)| tile.to_byte()));
+    bytes
+}
+
+/// Reads a grid written by [`grid_to_bytes`], returning it with the number of bytes it took up.
+pub fn grid_from_bytes<T: MapTile>(bytes: &[u8]) -> Result<(Grid<T>, usize), MapFormatError> {
+    if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(MapFormatError::BadHeader);
+    }
+    let width = u16::from_le_bytes([bytes[4], bytes[5]]) as usize;
+    let height = u16::from_le_bytes([bytes[6], bytes[7]]) as usize;
+    let len = HEADER_LEN + width * height;
+    if bytes.len() < len {
+        return Err(MapFormatError::Truncated);
+    }
+
+    let mut grid = Grid::new(width, height);
+    for ((pos, tile), byte) in grid.iter_mut().zip(&bytes[HEADER_LEN..len]) {
+        *tile = T::from_byte(*byte).ok_or(MapFormatError::UnknownByte { byte: *byte, pos })?;
+    }
+    Ok((grid, len))
+}
+
+impl GeneratedRoom {
+    /// Writes the walls, an empty line and then the floor of the room.
+    pub fn to_ascii(&self) -> String {
+        format!(
+            "{}\n\n{}\n",
+            grid_to_ascii(&self.walls),
+            grid_to_ascii(&self.floor)
+        )
+    }
+
+    /// Reads a room written by [`GeneratedRoom::to_ascii`]. The biome is not stored.
+    pub fn from_ascii(text: &str) -> Result<Self, MapFormatError> {
+        let text = text.replace("\r\n", "\n");
+        let (walls, floor) = text
+            .split_once("\n\n")
+            .ok_or(MapFormatError::MissingSection)?;
+        Self::from_grids(grid_from_ascii(walls)?, grid_from_ascii(floor)?)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = grid_to_bytes(&self.walls);
+        bytes.extend(grid_to_bytes(&self.floor));
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MapFormatError> {
+        let (walls, len) = grid_from_bytes(bytes)?;
+        let (floor, _) = grid_from_bytes(&bytes[len..])?;
+        Self::from_grids(walls, floor)
+    }
+
+    fn from_grids(walls: Grid<WallTile>, floor: Grid<FloorTile>) -> Result<Self, MapFormatError> {
+        let walls_size = Vector2i::new(walls.width() as i32, walls.height() as i32);
+        let floor_size = Vector2i::new(floor.width() as i32, floor.height() as i32);
+        if walls_size != floor_size {
+            return Err(MapFormatError::SizeMismatch {
+                walls: walls_size,
+                floor: floor_size,
+            });
+        }
+        Ok(Self {
+            walls,
+            floor,
+            biome: Default::default(),
+        })
+    }
+}
+
+impl fmt::Display for GeneratedRoom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_ascii())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALLS: &str = "#..#\n.To.\n####";
+    const FLOOR: &str = "_.,\"\n~*m%\n....";
+
+    fn sample_room() -> GeneratedRoom {
+        GeneratedRoom::from_ascii(&format!("{WALLS}\n\n{FLOOR}\n")).unwrap()
+    }
+
+    #[test]
+    fn every_tile_round_trips() {
+        let room = sample_room();
+        assert_eq!(grid_to_ascii(&room.walls), WALLS);
+        assert_eq!(grid_to_ascii(&room.floor), FLOOR);
+        assert_eq!(*room.floor.get(Vector2i::new(0, 0)), FloorTile::None);
+        assert_eq!(*room.walls.get(Vector2i::new(2, 1)), WallTile::Rock);
+    }
+
+    #[test]
+    fn ascii_round_trip() {
+        let text = sample_room().to_ascii();
+        assert_eq!(GeneratedRoom::from_ascii(&text).unwrap().to_ascii(), text);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let room = sample_room();
+        let bytes = room.to_bytes();
+        assert_eq!(bytes.len(), 2 * (HEADER_LEN + 4 * 3));
+        let loaded = GeneratedRoom::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.to_bytes(), bytes);
+        assert_eq!(loaded.to_ascii(), room.to_ascii());
+    }
+
+    #[test]
+    fn ascii_has_no_trailing_whitespace() {
+        let text = sample_room().to_ascii();
+        assert!(text.lines().all(|line| line.trim_end() == line));
+    }
+
+    #[test]
+    fn windows_line_endings_load() {
+        let text = sample_room().to_ascii().replace('\n', "\r\n");
+        let room = GeneratedRoom::from_ascii(&text).unwrap();
+        assert_eq!(room.to_ascii(), sample_room().to_ascii());
+    }
+
+    #[test]
+    fn malformed_ascii_is_rejected() {
+        assert_eq!(
+            grid_from_ascii::<WallTile>("#.\n#").unwrap_err(),
+            MapFormatError::RaggedRow { row: 1, width: 2 }
+        );
+        assert_eq!(
+            grid_from_ascii::<WallTile>("#x").unwrap_err(),
+            MapFormatError::UnknownChar {
+                ch: 'x',
+                pos: Vector2i::new(1, 0)
+            }
+        );
+        assert_eq!(
+            grid_from_ascii::<WallTile>("\n").unwrap_err(),
+            MapFormatError::Empty
+        );
+        assert_eq!(
+            GeneratedRoom::from_ascii("#.#").unwrap_err(),
+            MapFormatError::MissingSection
+        );
+    }
+
+    #[test]
+    fn floor_of_another_size_is_rejected() {
+        let expected = MapFormatError::SizeMismatch {
+            walls: Vector2i::new(4, 3),
+            floor: Vector2i::new(3, 3),
+        };
+        let text = format!("{WALLS}\n\n...\n...\n...\n");
+        assert_eq!(GeneratedRoom::from_ascii(&text).unwrap_err(), expected);
+
+        let room = sample_room();
+        let mut bytes = grid_to_bytes(&room.walls);
+        bytes.extend(grid_to_bytes(&Grid::<FloorTile>::new(3, 3)));
+        assert_eq!(GeneratedRoom::from_bytes(&bytes).unwrap_err(), expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "too large")]
+    fn oversized_grid_is_not_written() {
+        grid_to_bytes(&Grid::<WallTile>::new(u16::MAX as usize + 1, 1));
+    }
+
+    #[test]
+    fn malformed_bytes_are_rejected() {
+        let bytes = sample_room().to_bytes();
+        assert_eq!(
+            GeneratedRoom::from_bytes(b"GRIT\x01\x00\x01\x00\x00").unwrap_err(),
+            MapFormatError::BadHeader
+        );
+        assert_eq!(
+            GeneratedRoom::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(),
+            MapFormatError::Truncated
+        );
+        let mut unknown = bytes.clone();
+        unknown[HEADER_LEN] = 9;
+        assert_eq!(
+            GeneratedRoom::from_bytes(&unknown).unwrap_err(),
+            MapFormatError::UnknownByte {
+                byte: 9,
+                pos: Vector2i::ZERO
+            }
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use crate::room::generation::WallTile;
+use crate::utils::Grid;
+use godot::classes::{NavigationMeshSourceGeometryData2D, NavigationPolygon, NavigationServer2D};
+use godot::prelude::*;
+
+fn rect_outline(pos: Vector2, size: Vector2) -> PackedVector2Array {
+    PackedVector2Array::from(
+        &[
+            pos,
+            pos + Vector2::new(size.x, 0.0),
+            pos + size,
+            pos + Vector2::new(0.0, size.y),
+        ][..],
+    )
+}
+
+/// Bakes the navigation polygon of a room from its walls, in coordinates relative to the room.
+///
+/// Every cell that isn't clear is an obstruction, and the walkable area is shrunk by
+/// `agent_radius` so agents path around trees instead of scraping along them.
+pub fn bake_navigation_polygon(
+    walls: &Grid<WallTile>,
+    tile_size: Vector2i,
+    agent_radius: f32,
+) -> Gd<NavigationPolygon> {
+    let tile_size = Vector2::new(tile_size.x as real, tile_size.y as real);
+    let room_size = Vector2::new(walls.width() as real, walls.height() as real) * tile_size;
+
+    let mut source = NavigationMeshSourceGeometryData2D::new_gd();
+    source.add_traversable_outline(&rect_outline(Vector2::ZERO, room_size));
+    for (pos, tile) in walls {
+        if *tile != WallTile::Clear {
+            let cell_pos = Vector2::new(pos.x as real, pos.y as real) * tile_size;
+            source.add_obstruction_outline(&rect_outline(cell_pos, tile_size));
+        }
+    }
+
+    let mut polygon = NavigationPolygon::new_gd();
+    polygon.set_agent_radius(agent_radius);
+    NavigationServer2D::singleton().bake_from_source_geometry_data(&polygon, &source);
+    polygon
+}
@@ -0,0 +1,236 @@
+use crate::room::generation::{
+    Direction, FloorTile, GeneratedRoom, RoomLayout, RoomType, WallTile,
+};
+use godot::builtin::Vector2i;
+
+/// A hand-authored room, or a chunk of one, that gets stamped into a generated room.
+#[derive(Debug, Clone)]
+pub struct Prefab {
+    pub room: GeneratedRoom,
+    /// The sides of the prefab that are open to the rest of the room.
+    pub exits: RoomLayout,
+    pub weight: f32,
+    /// The only room type the prefab is used for, or `None` for all of them.
+    pub room_type: Option<RoomType>,
+}
+
+impl Prefab {
+    /// Whether every exit of `layout` can be reached through the prefab.
+    pub fn supports(&self, layout: &RoomLayout, room_type: RoomType) -> bool {
+        self.room_type.is_none_or(|only| only == room_type)
+            && layout.exits().all(|dir| self.exits.has_exit(dir))
+    }
+
+    /// Returns the declared exits that have no walkable cell on their side of the prefab.
+    pub fn missing_openings(&self) -> Vec<Direction> {
+        let walls = &self.room.walls;
+        let (width, height) = (walls.width(), walls.height());
+        self.exits
+            .exits()
+            .filter(|dir| {
+                let mut side: Box<dyn Iterator<Item = &WallTile>> = match dir {
+                    Direction::Top => Box::new(walls.row(0).iter()),
+                    Direction::Bottom => Box::new(walls.row(height - 1).iter()),
+                    Direction::Left => Box::new(walls.column(0)),
+                    Direction::Right => Box::new(walls.column(width - 1)),
+                };
+                !side.any(|tile| *tile == WallTile::Clear)
+            })
+            .collect()
+    }
+
+    /// Position of the top left corner of the prefab when centered in a room of the given size.
+    pub fn centered_origin(&self, width: usize, height: usize) -> Vector2i {
+        Vector2i::new(
+            (width as i32 - self.room.walls.width() as i32) / 2,
+            (height as i32 - self.room.walls.height() as i32) / 2,
+        )
+    }
+}
+
+/// Picks one of `prefabs` usable for the room, weighted by their weight. Prefabs made for the
+/// room type take priority over generic ones. `roll` is a random number in `[0, 1)`.
+pub fn pick_prefab<'a>(
+    prefabs: impl IntoIterator<Item = &'a Prefab>,
+    layout: &RoomLayout,
+    room_type: RoomType,
+    roll: f32,
+) -> Option<&'a Prefab> {
+    let usable: Vec<&Prefab> = prefabs
+        .into_iter()
+        .filter(|prefab| prefab.weight > 0.0 && prefab.supports(layout, room_type))
+        .collect();
+    let dedicated: Vec<&Prefab> = usable
+        .iter()
+        .copied()
+        .filter(|prefab| prefab.room_type.is_some())
+        .collect();
+    let candidates = if dedicated.is_empty() {
+        usable
+    } else {
+        dedicated
+    };
+    let total: f32 = candidates.iter().map(|prefab| prefab.weight).sum();
+
+    let mut remaining = roll * total;
+    for prefab in &candidates {
+        if remaining < prefab.weight {
+            return Some(prefab);
+        }
+        remaining -= prefab.weight;
+    }
+    candidates.last().copied()
+}
+
+impl GeneratedRoom {
+    /// Copies `prefab` into the room with its top left corner at `origin`.
+    ///
+    /// The border of the room is never touched, so the exits of its layout stay as they are. On
+    /// the outermost ring of the prefab only open ground is copied, which lets the walls of the
+    /// prefab merge into the generated ones instead of meeting them in a straight seam.
+    pub fn stamp(&mut self, prefab: &GeneratedRoom, origin: Vector2i) {
+        let (prefab_width, prefab_height) = (prefab.walls.width(), prefab.walls.height());
+        let room_width = self.walls.width() as i32;
+        let room_height = self.walls.height() as i32;
+        let tiles = prefab
+            .walls
+            .zip_with(&prefab.floor, |_, wall, floor| (*wall, *floor));
+
+        let area = self.walls.view(origin, prefab_width, prefab_height);
+        let stamped: Vec<(Vector2i, WallTile, FloorTile)> = area
+            .iter()
+            .filter_map(|(pos, _)| {
+                let target = area.origin() + pos;
+                let pos = target - origin;
+                let is_room_border = target.x == 0
+                    || target.y == 0
+                    || target.x == room_width - 1
+                    || target.y == room_height - 1;
+                let is_seam = pos.x == 0
+                    || pos.y == 0
+                    || pos.x == prefab_width as i32 - 1
+                    || pos.y == prefab_height as i32 - 1;
+                let (wall, floor) = *tiles.get(pos);
+                if is_room_border || (is_seam && wall != WallTile::Clear) {
+                    return None;
+                }
+                Some((target, wall, floor))
+            })
+            .collect();
+        for (target, wall, floor) in stamped {
+            self.walls.set(target, wall);
+            self.floor.set(target, floor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a room from the ASCII walls, with every floor tile set to the tile written as
+    /// `floor`.
+    fn room(walls: &str, floor: char) -> GeneratedRoom {
+        let floor: String = walls
+            .chars()
+            .map(|ch| if ch == '\n' { ch } else { floor })
+            .collect();
+        GeneratedRoom::from_ascii(&format!("{walls}\n\n{floor}\n")).unwrap()
+    }
+
+    fn layout(top: bool, bottom: bool, left: bool, right: bool) -> RoomLayout {
+        RoomLayout {
+            exit_top: top,
+            exit_bottom: bottom,
+            exit_left: left,
+            exit_right: right,
+        }
+    }
+
+    fn open_prefab(weight: f32, room_type: Option<RoomType>) -> Prefab {
+        Prefab {
+            room: room("...\n...\n...", '.'),
+            exits: layout(true, true, true, true),
+            weight,
+            room_type,
+        }
+    }
+
+    #[test]
+    fn dedicated_prefab_wins_over_generic_one() {
+        let prefabs = [
+            open_prefab(100.0, None),
+            open_prefab(1.0, Some(RoomType::Boss)),
+        ];
+        let exits = layout(true, false, false, true);
+        for roll in [0.0, 0.5, 0.99] {
+            let boss = pick_prefab(&prefabs, &exits, RoomType::Boss, roll).unwrap();
+            assert!(std::ptr::eq(boss, &prefabs[1]));
+            let normal = pick_prefab(&prefabs, &exits, RoomType::Normal, roll).unwrap();
+            assert!(std::ptr::eq(normal, &prefabs[0]));
+        }
+    }
+
+    #[test]
+    fn zero_weight_prefabs_are_never_picked() {
+        let prefabs = [open_prefab(0.0, None), open_prefab(1.0, None)];
+        let exits = layout(true, true, true, true);
+        for roll in [0.0, 0.5, 0.99] {
+            let picked = pick_prefab(&prefabs, &exits, RoomType::Normal, roll).unwrap();
+            assert!(std::ptr::eq(picked, &prefabs[1]));
+        }
+        assert!(pick_prefab(&prefabs[..1], &exits, RoomType::Normal, 0.0).is_none());
+    }
+
+    #[test]
+    fn prefab_without_a_required_exit_is_rejected() {
+        let mut prefab = open_prefab(1.0, None);
+        prefab.exits = layout(true, false, false, false);
+        assert!(prefab.supports(&layout(true, false, false, false), RoomType::Normal));
+        assert!(!prefab.supports(&layout(true, false, true, false), RoomType::Normal));
+        let prefabs = [prefab];
+        let exits = layout(true, false, true, false);
+        assert!(pick_prefab(&prefabs, &exits, RoomType::Normal, 0.0).is_none());
+    }
+
+    #[test]
+    fn missing_openings_lists_closed_sides() {
+        let prefab = Prefab {
+            room: room("#.#\n#..\n###", '.'),
+            exits: layout(true, true, true, true),
+            weight: 1.0,
+            room_type: None,
+        };
+        assert_eq!(
+            prefab.missing_openings(),
+            vec![Direction::Bottom, Direction::Left]
+        );
+    }
+
+    #[test]
+    fn stamp_leaves_room_border_untouched() {
+        let mut target = room("#####\n#####\n#####\n#####\n#####", '.');
+        let prefab = room(".....\n.....\n.....\n.....\n.....", ',');
+        target.stamp(&prefab, Vector2i::new(0, 0));
+        assert_eq!(
+            target.to_ascii(),
+            "#####\n#...#\n#...#\n#...#\n#####\n\n\
+             .....\n.,,,.\n.,,,.\n.,,,.\n.....\n"
+        );
+    }
+
+    #[test]
+    fn stamp_copies_only_open_ground_on_the_seam() {
+        let mut target = room(
+            "#######\n#.....#\n#.....#\n#.....#\n#.....#\n#.....#\n#######",
+            '.',
+        );
+        let prefab = room("#.###\n#####\n##.##\n#####\n#####", ',');
+        target.stamp(&prefab, Vector2i::new(1, 1));
+        assert_eq!(
+            target.to_ascii(),
+            "#######\n#.....#\n#.###.#\n#.#.#.#\n#.###.#\n#.....#\n#######\n\n\
+             .......\n..,....\n..,,,..\n..,,,..\n..,,,..\n.......\n.......\n"
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use crate::room::generation::{GeneratedRoom, RoomLayout, RoomType};
+use crate::room::prefab::Prefab;
+use godot::prelude::*;
+use std::cell::OnceCell;
+
+/// A hand-authored room or chunk of a room, stamped into the center of generated rooms.
+///
+/// The map is parsed the first time the prefab is used, so later changes to the resource are not
+/// picked up.
+#[derive(GodotClass)]
+#[class(base=Resource)]
+pub struct RoomPrefab {
+    /// The walls, an empty line and the floor, in the format written by `Room.save_map`.
+    #[export(multiline)]
+    map: GString,
+
+    #[export]
+    exit_top: bool,
+
+    #[export]
+    exit_bottom: bool,
+
+    #[export]
+    exit_left: bool,
+
+    #[export]
+    exit_right: bool,
+
+    /// How often the prefab is picked compared to the other ones.
+    #[export]
+    weight: f32,
+
+    /// The only room type the prefab is used for.
+    #[export(enum = (Any, Start, Normal, Treasure, Shop, Boss, Safe))]
+    room_type: i32,
+
+    prefab: OnceCell<Option<Prefab>>,
+
+    base: Base<Resource>,
+}
+
+#[godot_api]
+impl IResource for RoomPrefab {
+    fn init(base: Base<Resource>) -> Self {
+        Self {
+            map: GString::new(),
+            exit_top: true,
+            exit_bottom: true,
+            exit_left: true,
+            exit_right: true,
+            weight: 1.0,
+            room_type: 0,
+            prefab: OnceCell::new(),
+            base,
+        }
+    }
+}
+
+impl RoomPrefab {
+    /// Returns the parsed prefab, or `None` if its map is invalid.
+    pub fn prefab(&self) -> Option<&Prefab> {
+        self.prefab.get_or_init(|| self.parse()).as_ref()
+    }
+
+    fn parse(&self) -> Option<Prefab> {
+        let room = match GeneratedRoom::from_ascii(&self.map.to_string()) {
+            Ok(room) => room,
+            Err(err) => {
+                godot_error!("Invalid prefab map in {}: {err}", self.base().get_path());
+                return None;
+            }
+        };
+        if room.walls.width() != room.floor.width() || room.walls.height() != room.floor.height() {
+            godot_error!(
+                "Prefab {} has walls and floor of different sizes",
+                self.base().get_path()
+            );
+            return None;
+        }
+        let room_type = match self.room_type {
+            1 => Some(RoomType::Start),
+            2 => Some(RoomType::Normal),
+            3 => Some(RoomType::Treasure),
+            4 => Some(RoomType::Shop),
+            5 => Some(RoomType::Boss),
+            6 => Some(RoomType::Safe),
+            _ => None,
+        };
+        let prefab = Prefab {
+            room,
+            exits: RoomLayout {
+                exit_top: self.exit_top,
+                exit_bottom: self.exit_bottom,
+                exit_left: self.exit_left,
+                exit_right: self.exit_right,
+            },
+            weight: self.weight,
+            room_type,
+        };
+
+        let missing = prefab.missing_openings();
+        if !missing.is_empty() {
+            godot_warn!(
+                "Prefab {} declares exits {missing:?} without an opening",
+                self.base().get_path()
+            );
+        }
+        Some(prefab)
+    }
+}
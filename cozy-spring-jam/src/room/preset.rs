@@ -0,0 +1,132 @@
+use crate::room::biome::Biome;
+use crate::room::generation::RoomGenParams;
+use godot::prelude::*;
+
+/// Tunable room generation parameters that can be saved as a resource and shared between rooms.
+#[derive(GodotClass)]
+#[class(base=Resource)]
+pub struct RoomGenPreset {
+    #[export]
+    growth_falloff: f32,
+
+    #[export]
+    edge_growth: f32,
+
+    #[export]
+    center_growth: f32,
+
+    #[export]
+    growth_noise_amplitude: f32,
+
+    #[export]
+    growth_noise_frequency: f32,
+
+    #[export]
+    grow_noise_octaves: i32,
+
+    #[export]
+    growth_noise_bias: f32,
+
+    #[export]
+    special_noise_frequency: f32,
+
+    #[export]
+    special_noise_amplitude: f32,
+
+    #[export]
+    noise_fractal_gain: f32,
+
+    #[export]
+    tree_growth_cutoff: f32,
+
+    #[export]
+    lone_tree_growth_cutoff: f32,
+
+    #[export]
+    lone_tree_special_cutoff: f32,
+
+    #[export]
+    grass_growth_cutoff: f32,
+
+    #[export]
+    tall_grass_growth_cutoff: f32,
+
+    #[export]
+    exit_size: i32,
+
+    #[export]
+    max_carved_cells: i32,
+
+    #[export]
+    max_attempts: i32,
+
+    /// The only biome the preset is used for.
+    #[export(enum = (Any, Meadow, Forest, Marsh, Rocky))]
+    biome: i32,
+
+    base: Base<Resource>,
+}
+
+#[godot_api]
+impl IResource for RoomGenPreset {
+    fn init(base: Base<Resource>) -> Self {
+        let params = RoomGenParams::default();
+        Self {
+            growth_falloff: params.growth_falloff,
+            edge_growth: params.edge_growth,
+            center_growth: params.center_growth,
+            growth_noise_amplitude: params.growth_noise_amplitude,
+            growth_noise_frequency: params.growth_noise_frequency,
+            grow_noise_octaves: params.grow_noise_octaves,
+            growth_noise_bias: params.growth_noise_bias,
+            special_noise_frequency: params.special_noise_frequency,
+            special_noise_amplitude: params.special_noise_amplitude,
+            noise_fractal_gain: params.noise_fractal_gain,
+            tree_growth_cutoff: params.tree_growth_cutoff,
+            lone_tree_growth_cutoff: params.lone_tree_growth_cutoff,
+            lone_tree_special_cutoff: params.lone_tree_special_cutoff,
+            grass_growth_cutoff: params.grass_growth_cutoff,
+            tall_grass_growth_cutoff: params.tall_grass_growth_cutoff,
+            exit_size: params.exit_size as i32,
+            max_carved_cells: params.max_carved_cells as i32,
+            max_attempts: params.max_attempts as i32,
+            biome: 0,
+            base,
+        }
+    }
+}
+
+impl RoomGenPreset {
+    pub fn biome(&self) -> Option<Biome> {
+        match self.biome {
+            1 => Some(Biome::Meadow),
+            2 => Some(Biome::Forest),
+            3 => Some(Biome::Marsh),
+            4 => Some(Biome::Rocky),
+            _ => None,
+        }
+    }
+
+    pub fn to_params(&self) -> RoomGenParams {
+        RoomGenParams {
+            growth_falloff: self.growth_falloff,
+            edge_growth: self.edge_growth,
+            center_growth: self.center_growth,
+            growth_noise_amplitude: self.growth_noise_amplitude,
+            growth_noise_frequency: self.growth_noise_frequency,
+            grow_noise_octaves: self.grow_noise_octaves,
+            growth_noise_bias: self.growth_noise_bias,
+            special_noise_frequency: self.special_noise_frequency,
+            special_noise_amplitude: self.special_noise_amplitude,
+            noise_fractal_gain: self.noise_fractal_gain,
+            tree_growth_cutoff: self.tree_growth_cutoff,
+            lone_tree_growth_cutoff: self.lone_tree_growth_cutoff,
+            lone_tree_special_cutoff: self.lone_tree_special_cutoff,
+            grass_growth_cutoff: self.grass_growth_cutoff,
+            tall_grass_growth_cutoff: self.tall_grass_growth_cutoff,
+            exit_size: self.exit_size.max(1) as u32,
+            max_carved_cells: self.max_carved_cells.max(0) as usize,
+            max_attempts: self.max_attempts.max(1) as u32,
+        }
+    }
+}
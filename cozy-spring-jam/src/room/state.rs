@@ -0,0 +1,33 @@
+use crate::room::generation::{RoomLayout, RoomType};
+use godot::builtin::{Vector2, Vector2i};
+
+#[derive(Debug, Clone)]
+pub struct EnemyState {
+    pub scene_path: String,
+    pub position: Vector2,
+    pub health: i16,
+    pub gun_seed: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DropState {
+    pub scene_path: String,
+    pub position: Vector2,
+}
+
+/// Everything needed to bring back an unloaded room exactly as the player left it.
+///
+/// The tiles are not stored, as they are regenerated from `seed` and `layout`. Only the walls
+/// destroyed since then are remembered.
+#[derive(Debug, Clone)]
+pub struct RoomState {
+    pub seed: u64,
+    pub room_type: RoomType,
+    pub layout: RoomLayout,
+    pub cleared: bool,
+    /// Waves of enemies still to come once the current ones are defeated.
+    pub waves_left: u32,
+    pub destroyed_walls: Vec<Vector2i>,
+    pub enemies: Vec<EnemyState>,
+    pub drops: Vec<DropState>,
+}
@@ -0,0 +1,247 @@
+use crate::room::generation::WallTile;
+use crate::utils::Grid;
+use godot::builtin::Vector2i;
+use std::collections::{HashMap, VecDeque};
+
+fn is_border(grid: &Grid<WallTile>, pos: Vector2i) -> bool {
+    pos.x == 0
+        || pos.y == 0
+        || pos.x as usize == grid.width() - 1
+        || pos.y as usize == grid.height() - 1
+}
+
+fn is_walkable(tile: &WallTile) -> bool {
+    *tile == WallTile::Clear
+}
+
+/// Labels every walkable cell with the index of the 4-connected region it belongs to.
+fn find_regions(grid: &Grid<WallTile>) -> (HashMap<Vector2i, usize>, Vec<Vec<Vector2i>>) {
+    let regions = grid.regions(is_walkable);
+    let labels = regions
+        .iter()
+        .enumerate()
+        .flat_map(|(label, region)| region.iter().map(move |pos| (*pos, label)))
+        .collect();
+    (labels, regions)
+}
+
+/// Finds the shortest path from `region` to any cell labelled `target`, allowed to pass through
+/// any cell except the walls of the room border, unless `through_border` is set.
+fn find_corridor(
+    grid: &Grid<WallTile>,
+    labels: &HashMap<Vector2i, usize>,
+    region: &[Vector2i],
+    target: usize,
+    through_border: bool,
+) -> Option<Vec<Vector2i>> {
+    let mut came_from: HashMap<Vector2i, Option<Vector2i>> = HashMap::new();
+    let mut queue = VecDeque::new();
+    for pos in region {
+        came_from.insert(*pos, None);
+        queue.push_back(*pos);
+    }
+
+    while let Some(pos) = queue.pop_front() {
+        if labels.get(&pos) == Some(&target) {
+            let mut path = Vec::new();
+            let mut current = Some(pos);
+            while let Some(step) = current {
+                path.push(step);
+                current = came_from[&step];
+            }
+            return Some(path);
+        }
+
+        for next in grid.neighbours_4(pos) {
+            if came_from.contains_key(&next) {
+                continue;
+            }
+            if !through_border && is_border(grid, next) && !is_walkable(grid.get(next)) {
+                continue;
+            }
+            came_from.insert(next, Some(pos));
+            queue.push_back(next);
+        }
+    }
+    None
+}
+
+/// Connects all walkable floor of a room into a single region, which includes every exit.
+///
+/// Isolated regions are joined to the region of the first exit by carving the shortest corridor
+/// through walls and trees. Isolated regions without an exit that can't be reached without
+/// breaking the border are filled with walls instead. Returns the number of cells that had to
+/// be carved.
+pub fn connect_regions(grid: &mut Grid<WallTile>) -> usize {
+    let mut carved = 0;
+
+    loop {
+        let (labels, regions) = find_regions(grid);
+        if regions.len() <= 1 {
+            if regions.is_empty() {
+                let center = Vector2i::new(grid.width() as i32 / 2, grid.height() as i32 / 2);
+                grid.set(center, WallTile::Clear);
+                carved += 1;
+            }
+            return carved;
+        }
+
+        // Exits are the only walkable cells on the border, so the main region is the one
+        // reaching the first exit, or the largest one for rooms without exits.
+        let main = regions
+            .iter()
+            .position(|region| region.iter().any(|pos| is_border(grid, *pos)))
+            .unwrap_or_else(|| {
+                (0..regions.len())
+                    .max_by_key(|idx| regions[*idx].len())
+                    .unwrap_or_default()
+            });
+
+        let isolated = if main == 0 { 1 } else { 0 };
+        let region = &regions[isolated];
+        let has_exit = region.iter().any(|pos| is_border(grid, *pos));
+        // Only exits squeezed into a corner have no way into the room except along the border
+        let corridor = match find_corridor(grid, &labels, region, main, false) {
+            None if has_exit => find_corridor(grid, &labels, region, main, true),
+            corridor => corridor,
+        };
+        match corridor {
+            Some(path) => {
+                for pos in path {
+                    if !is_walkable(grid.get(pos)) {
+                        grid.set(pos, WallTile::Clear);
+                        carved += 1;
+                    }
+                }
+            }
+            None => {
+                // Nothing can reach the main region without breaking the border, and there is no
+                // exit to keep open, so seal it off.
+                for pos in region {
+                    grid.set(*pos, WallTile::Wall);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the walkable cells on the border of the room, which make up its exits.
+pub fn exit_cells(grid: &Grid<WallTile>) -> Vec<Vector2i> {
+    grid.iter()
+        .filter(|(pos, tile)| is_walkable(tile) && is_border(grid, *pos))
+        .map(|(pos, _)| pos)
+        .collect()
+}
+
+/// Returns the number of steps from every walkable cell to the closest exit.
+pub fn exit_distances(grid: &Grid<WallTile>) -> Grid<Option<u32>> {
+    grid.distance_map(exit_cells(grid), is_walkable)
+}
+
+/// Returns the number of steps from `from` to every walkable cell it can reach.
+pub fn walking_distances(grid: &Grid<WallTile>, from: Vector2i) -> Grid<Option<u32>> {
+    grid.distance_map([from], is_walkable)
+}
+
+/// Returns every walkable cell inside the room, leaving out the exit gaps in the border.
+pub fn walkable_cells(grid: &Grid<WallTile>) -> Vec<Vector2i> {
+    grid.iter()
+        .filter(|(pos, tile)| is_walkable(tile) && !is_border(grid, *pos))
+        .map(|(pos, _)| pos)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::room::map_format::grid_from_ascii;
+
+    fn walls(text: &str) -> Grid<WallTile> {
+        grid_from_ascii(text).unwrap()
+    }
+
+    /// Asserts that every walkable cell, exits included, can be walked to from the first exit.
+    fn assert_connected(grid: &Grid<WallTile>) {
+        let exits = exit_cells(grid);
+        let from = exits
+            .first()
+            .copied()
+            .unwrap_or_else(|| walkable_cells(grid)[0]);
+        let distances = walking_distances(grid, from);
+        for (pos, tile) in grid.iter() {
+            if is_walkable(tile) {
+                assert!(distances.get(pos).is_some(), "{pos:?} is unreachable");
+            }
+        }
+    }
+
+    #[test]
+    fn connects_isolated_pocket() {
+        let mut grid = walls(
+            "###.###\n\
+             #.....#\n\
+             #.....#\n\
+             #.###.#\n\
+             #.#.#.#\n\
+             #.###.#\n\
+             #######",
+        );
+        assert_eq!(connect_regions(&mut grid), 1);
+        assert_connected(&grid);
+        assert_eq!(*grid.get(Vector2i::new(3, 4)), WallTile::Clear);
+    }
+
+    #[test]
+    fn connects_exits_split_into_two_regions() {
+        let mut grid = walls(
+            "###.###\n\
+             #.....#\n\
+             #.....#\n\
+             #######\n\
+             #.....#\n\
+             #.....#\n\
+             ###.###",
+        );
+        assert_eq!(connect_regions(&mut grid), 1);
+        assert_connected(&grid);
+        assert_eq!(exit_cells(&grid).len(), 2);
+    }
+
+    #[test]
+    fn carves_through_all_wall_interior() {
+        let mut grid = walls(
+            "###.###\n\
+             #######\n\
+             #######\n\
+             .######\n\
+             #######\n\
+             #######\n\
+             ###.###",
+        );
+        assert!(connect_regions(&mut grid) > 0);
+        assert_connected(&grid);
+        assert_eq!(exit_cells(&grid).len(), 3);
+    }
+
+    #[test]
+    fn never_seals_exits() {
+        let mut grid = walls(
+            ".######\n\
+             #######\n\
+             ###.###\n\
+             #######\n\
+             ###.###",
+        );
+        connect_regions(&mut grid);
+        assert_connected(&grid);
+        assert_eq!(*grid.get(Vector2i::new(0, 0)), WallTile::Clear);
+        assert_eq!(*grid.get(Vector2i::new(3, 4)), WallTile::Clear);
+    }
+
+    #[test]
+    fn fills_empty_room_from_the_center() {
+        let mut grid = walls("#####\n#####\n#####");
+        assert_eq!(connect_regions(&mut grid), 1);
+        assert_eq!(walkable_cells(&grid), vec![Vector2i::new(2, 1)]);
+    }
+}
@@ -6,6 +6,7 @@ use godot::builtin::Vector2i;
 struct GridSize {
     width: usize,
     height: usize,
+    toroidal: bool,
 }
 
 impl GridSize {
@@ -17,16 +18,29 @@ impl GridSize {
         self.height
     }
 
-    fn pos_to_idx(&self, pos: Vector2i) -> usize {
-        let mut norm_x = (pos.x.unsigned_abs() as usize) % self.width();
-        if pos.x.is_negative() {
-            norm_x = self.width() - norm_x;
+    fn contains(&self, pos: Vector2i) -> bool {
+        if self.toroidal {
+            return self.width > 0 && self.height > 0;
         }
-        let mut norm_y = (pos.y.unsigned_abs() as usize) % self.height();
-        if pos.y.is_negative() {
-            norm_y = self.height() - norm_y;
+        pos.x >= 0 && pos.y >= 0 && (pos.x as usize) < self.width && (pos.y as usize) < self.height
+    }
+
+    fn wrap(&self, pos: Vector2i) -> Vector2i {
+        if !self.toroidal || self.width == 0 || self.height == 0 {
+            return pos;
+        }
+        Vector2i::new(
+            pos.x.rem_euclid(self.width as i32),
+            pos.y.rem_euclid(self.height as i32),
+        )
+    }
+
+    fn pos_to_idx(&self, pos: Vector2i) -> Option<usize> {
+        if !self.contains(pos) {
+            return None;
         }
-        norm_y * self.width() + norm_x
+        let pos = self.wrap(pos);
+        Some(pos.y as usize * self.width + pos.x as usize)
     }
 
     fn idx_to_pos(&self, idx: usize) -> Vector2i {
@@ -48,13 +62,30 @@ where
 {
     pub fn new(width: usize, height: usize) -> Self {
         Self {
-            size: GridSize { width, height },
+            size: GridSize {
+                width,
+                height,
+                toroidal: false,
+            },
             entries: vec![T::default(); width * height],
         }
     }
 }
 
 impl<T> Grid<T> {
+    /// Creates a grid by calling `f` with the position of every entry, row by row.
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(Vector2i) -> T) -> Self {
+        let size = GridSize {
+            width,
+            height,
+            toroidal: false,
+        };
+        let entries = (0..width * height)
+            .map(|idx| f(size.idx_to_pos(idx)))
+            .collect();
+        Self { size, entries }
+    }
+
     pub fn width(&self) -> usize {
         self.size.width()
     }
@@ -63,22 +94,139 @@ impl<T> Grid<T> {
         self.size.height()
     }
 
+    /// Makes positions outside of the grid wrap around to the opposite edge, like on a torus.
+    pub fn with_toroidal(mut self, toroidal: bool) -> Self {
+        self.size.toroidal = toroidal;
+        self
+    }
+
+    pub fn is_toroidal(&self) -> bool {
+        self.size.toroidal
+    }
+
+    /// Whether `pos` refers to an entry of the grid. Always true for non-empty toroidal grids.
+    pub fn contains(&self, pos: Vector2i) -> bool {
+        self.size.contains(pos)
+    }
+
+    /// Maps `pos` onto the grid by wrapping it around the edges of toroidal grids. Positions on
+    /// other grids are returned as they are.
+    pub fn wrap(&self, pos: Vector2i) -> Vector2i {
+        self.size.wrap(pos)
+    }
+
+    pub fn try_get(&self, pos: Vector2i) -> Option<&T> {
+        let idx = self.size.pos_to_idx(pos)?;
+        self.entries.get(idx)
+    }
+
+    pub fn try_get_mut(&mut self, pos: Vector2i) -> Option<&mut T> {
+        let idx = self.size.pos_to_idx(pos)?;
+        self.entries.get_mut(idx)
+    }
+
+    /// # Panics
+    ///
+    /// If `pos` is outside of a grid that isn't toroidal.
     pub fn get(&self, pos: Vector2i) -> &T {
-        self.entries
-            .get(self.size.pos_to_idx(pos))
-            .expect("error while accessing grid entries")
+        let (width, height) = (self.width(), self.height());
+        self.try_get(pos).unwrap_or_else(|| {
+            panic!("position {pos:?} is out of bounds for a {width}x{height} grid")
+        })
     }
 
+    /// # Panics
+    ///
+    /// If `pos` is outside of a grid that isn't toroidal.
     pub fn get_mut(&mut self, pos: Vector2i) -> &mut T {
-        self.entries
-            .get_mut(self.size.pos_to_idx(pos))
-            .expect("error while accessing grid entries")
+        let (width, height) = (self.width(), self.height());
+        self.try_get_mut(pos).unwrap_or_else(|| {
+            panic!("position {pos:?} is out of bounds for a {width}x{height} grid")
+        })
     }
 
     pub fn set(&mut self, pos: Vector2i, value: T) {
         *self.get_mut(pos) = value;
     }
 
+    /// Creates a grid of the same size from the result of `f` for every entry.
+    pub fn map<U>(&self, mut f: impl FnMut(Vector2i, &T) -> U) -> Grid<U> {
+        Grid {
+            size: self.size.clone(),
+            entries: self.iter().map(|(pos, entry)| f(pos, entry)).collect(),
+        }
+    }
+
+    /// Combines the entries at the same position of two grids of equal size.
+    ///
+    /// # Panics
+    ///
+    /// If the grids differ in size.
+    pub fn zip_with<U, V>(
+        &self,
+        other: &Grid<U>,
+        mut f: impl FnMut(Vector2i, &T, &U) -> V,
+    ) -> Grid<V> {
+        assert!(
+            self.width() == other.width() && self.height() == other.height(),
+            "can't zip a {}x{} grid with a {}x{} grid",
+            self.width(),
+            self.height(),
+            other.width(),
+            other.height()
+        );
+        Grid {
+            size: self.size.clone(),
+            entries: self
+                .iter()
+                .zip(other.entries.iter())
+                .map(|((pos, a), b)| f(pos, a, b))
+                .collect(),
+        }
+    }
+
+    /// Returns the entries of row `y`, from left to right.
+    ///
+    /// # Panics
+    ///
+    /// If `y` is outside of the grid.
+    pub fn row(&self, y: usize) -> &[T] {
+        assert!(
+            y < self.height(),
+            "row {y} is out of bounds for a grid of height {}",
+            self.height()
+        );
+        &self.entries[y * self.width()..(y + 1) * self.width()]
+    }
+
+    /// Iterates over the entries of column `x`, from top to bottom.
+    ///
+    /// # Panics
+    ///
+    /// If `x` is outside of the grid.
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> + '_ {
+        assert!(
+            x < self.width(),
+            "column {x} is out of bounds for a grid of width {}",
+            self.width()
+        );
+        self.entries.iter().skip(x).step_by(self.width())
+    }
+
+    /// Returns a view of the `width` by `height` rectangle starting at `origin`, clipped to the
+    /// grid.
+    pub fn view(&self, origin: Vector2i, width: usize, height: usize) -> GridView<'_, T> {
+        let min = Vector2i::new(origin.x.max(0), origin.y.max(0));
+        let max_x = (origin.x + width as i32).min(self.width() as i32);
+        let max_y = (origin.y + height as i32).min(self.height() as i32);
+        GridView {
+            grid: self,
+            origin: min,
+            width: (max_x - min.x).max(0) as usize,
+            height: (max_y - min.y).max(0) as usize,
+        }
+    }
+
     pub fn iter(&self) -> Iter<'_, T> {
         Iter {
             size: self.size.clone(),
@@ -100,6 +248,7 @@ impl<T> Default for Grid<T> {
             size: GridSize {
                 width: 0,
                 height: 0,
+                toroidal: false,
             },
             entries: Vec::new(),
         }
@@ -151,7 +300,7 @@ pub struct IterMut<'a, T> {
 }
 
 impl<'a, T> Iterator for IterMut<'a, T> {
-    type Item = (Vector2i, &'a T);
+    type Item = (Vector2i, &'a mut T);
 
     fn next(&mut self) -> Option<Self::Item> {
         let (idx, item) = self.iter.next()?;
@@ -165,3 +314,130 @@ impl<'a, T> ExactSizeIterator for IterMut<'a, T> {
         self.iter.len()
     }
 }
+
+/// A rectangular part of a grid. Positions are relative to the origin of the view.
+pub struct GridView<'a, T> {
+    grid: &'a Grid<T>,
+    origin: Vector2i,
+    width: usize,
+    height: usize,
+}
+
+impl<'a, T> GridView<'a, T> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Position of the top left entry of the view in the grid.
+    pub fn origin(&self) -> Vector2i {
+        self.origin
+    }
+
+    /// Iterates over the entries of the view, with positions relative to the view.
+    pub fn iter(&self) -> impl Iterator<Item = (Vector2i, &'a T)> + '_ {
+        (0..self.height).flat_map(move |y| {
+            (0..self.width).map(move |x| {
+                let pos = Vector2i::new(x as i32, y as i32);
+                (pos, self.grid.get(self.origin + pos))
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered(width: usize, height: usize) -> Grid<i32> {
+        Grid::from_fn(width, height, |pos| pos.y * 10 + pos.x)
+    }
+
+    #[test]
+    fn from_fn_fills_row_by_row() {
+        let grid = numbered(3, 2);
+        let entries: Vec<i32> = grid.iter().map(|(_, entry)| *entry).collect();
+        assert_eq!(entries, vec![0, 1, 2, 10, 11, 12]);
+    }
+
+    #[test]
+    fn out_of_bounds_access() {
+        let grid = numbered(3, 2);
+        assert_eq!(grid.try_get(Vector2i::new(3, 0)), None);
+        assert_eq!(grid.try_get(Vector2i::new(0, -1)), None);
+        assert_eq!(grid.try_get(Vector2i::new(2, 1)), Some(&12));
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn get_panics_outside_of_grid() {
+        numbered(3, 2).get(Vector2i::new(-1, 0));
+    }
+
+    #[test]
+    fn toroidal_access_wraps() {
+        let grid = numbered(3, 2).with_toroidal(true);
+        assert_eq!(*grid.get(Vector2i::new(-1, 0)), 2);
+        assert_eq!(*grid.get(Vector2i::new(4, 3)), 11);
+        assert_eq!(grid.wrap(Vector2i::new(-1, -1)), Vector2i::new(2, 1));
+    }
+
+    #[test]
+    fn iter_mut_changes_entries() {
+        let mut grid = numbered(2, 2);
+        for (pos, entry) in grid.iter_mut() {
+            *entry += pos.x;
+        }
+        assert_eq!(*grid.get(Vector2i::new(1, 1)), 12);
+    }
+
+    #[test]
+    fn map_and_zip_keep_positions() {
+        let grid = numbered(3, 2);
+        let doubled = grid.map(|_, entry| entry * 2);
+        assert_eq!(*doubled.get(Vector2i::new(1, 1)), 22);
+
+        let zipped = grid.zip_with(&doubled, |pos, a, b| (pos, a + b));
+        assert_eq!(*zipped.get(Vector2i::new(2, 1)), (Vector2i::new(2, 1), 36));
+    }
+
+    #[test]
+    #[should_panic(expected = "can't zip")]
+    fn zip_with_panics_on_size_mismatch() {
+        numbered(3, 2).zip_with(&numbered(2, 3), |_, a, b| a + b);
+    }
+
+    #[test]
+    fn rows_and_columns() {
+        let grid = numbered(3, 2);
+        assert_eq!(grid.row(1), &[10, 11, 12]);
+        assert_eq!(grid.column(2).copied().collect::<Vec<_>>(), vec![2, 12]);
+    }
+
+    #[test]
+    fn view_is_clipped_to_grid() {
+        let grid = numbered(4, 3);
+        let view = grid.view(Vector2i::new(-1, 1), 3, 5);
+        assert_eq!(view.origin(), Vector2i::new(0, 1));
+        assert_eq!((view.width(), view.height()), (2, 2));
+        let entries: Vec<(Vector2i, i32)> = view.iter().map(|(pos, entry)| (pos, *entry)).collect();
+        assert_eq!(
+            entries,
+            vec![
+                (Vector2i::new(0, 0), 10),
+                (Vector2i::new(1, 0), 11),
+                (Vector2i::new(0, 1), 20),
+                (Vector2i::new(1, 1), 21),
+            ]
+        );
+    }
+
+    #[test]
+    fn view_outside_of_grid_is_empty() {
+        let grid = numbered(4, 3);
+        assert_eq!(grid.view(Vector2i::new(5, 5), 2, 2).iter().count(), 0);
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::VecDeque;
+
+use godot::builtin::Vector2i;
+
+use crate::utils::Grid;
+
+pub const NEIGHBOURS_4: [Vector2i; 4] = [
+    Vector2i::new(1, 0),
+    Vector2i::new(-1, 0),
+    Vector2i::new(0, 1),
+    Vector2i::new(0, -1),
+];
+
+pub const NEIGHBOURS_8: [Vector2i; 8] = [
+    Vector2i::new(1, 0),
+    Vector2i::new(-1, 0),
+    Vector2i::new(0, 1),
+    Vector2i::new(0, -1),
+    Vector2i::new(1, 1),
+    Vector2i::new(-1, 1),
+    Vector2i::new(1, -1),
+    Vector2i::new(-1, -1),
+];
+
+impl<T> Grid<T> {
+    /// Iterates over the up to four orthogonal neighbours of `pos` inside the grid. Neighbours
+    /// wrap around the edges of toroidal grids.
+    pub fn neighbours_4(&self, pos: Vector2i) -> impl Iterator<Item = Vector2i> + '_ {
+        NEIGHBOURS_4
+            .iter()
+            .map(move |dir| pos + *dir)
+            .filter(|next| self.contains(*next))
+            .map(|next| self.wrap(next))
+    }
+
+    /// Iterates over the up to eight orthogonal and diagonal neighbours of `pos` inside the grid.
+    /// Neighbours wrap around the edges of toroidal grids.
+    pub fn neighbours_8(&self, pos: Vector2i) -> impl Iterator<Item = Vector2i> + '_ {
+        NEIGHBOURS_8
+            .iter()
+            .map(move |dir| pos + *dir)
+            .filter(|next| self.contains(*next))
+            .map(|next| self.wrap(next))
+    }
+
+    /// Returns every cell 4-connected to `start` through passable cells, `start` first.
+    ///
+    /// Returns nothing if `start` itself is not passable.
+    pub fn flood_fill(&self, start: Vector2i, passable: impl Fn(&T) -> bool) -> Vec<Vector2i> {
+        if !self.contains(start) || !passable(self.get(start)) {
+            return Vec::new();
+        }
+        let start = self.wrap(start);
+
+        let mut visited =
+            Grid::<bool>::new(self.width(), self.height()).with_toroidal(self.is_toroidal());
+        let mut region = Vec::new();
+        let mut queue = VecDeque::from([start]);
+        visited.set(start, true);
+        while let Some(pos) = queue.pop_front() {
+            region.push(pos);
+            for next in self.neighbours_4(pos) {
+                if !*visited.get(next) && passable(self.get(next)) {
+                    visited.set(next, true);
+                    queue.push_back(next);
+                }
+            }
+        }
+        region
+    }
+
+    /// Splits all passable cells into 4-connected regions, in the order their first cell appears
+    /// in the grid.
+    pub fn regions(&self, passable: impl Fn(&T) -> bool) -> Vec<Vec<Vector2i>> {
+        let mut visited =
+            Grid::<bool>::new(self.width(), self.height()).with_toroidal(self.is_toroidal());
+        let mut regions = Vec::new();
+        for (pos, tile) in self {
+            if *visited.get(pos) || !passable(tile) {
+                continue;
+            }
+            let region = self.flood_fill(pos, &passable);
+            for cell in &region {
+                visited.set(*cell, true);
+            }
+            regions.push(region);
+        }
+        regions
+    }
+
+    /// Returns the number of 4-connected steps from each cell to the closest of `sources`,
+    /// walking through passable cells only. Unreachable cells are `None`.
+    pub fn distance_map(
+        &self,
+        sources: impl IntoIterator<Item = Vector2i>,
+        passable: impl Fn(&T) -> bool,
+    ) -> Grid<Option<u32>> {
+        let mut distances =
+            Grid::<Option<u32>>::new(self.width(), self.height()).with_toroidal(self.is_toroidal());
+        let mut queue = VecDeque::new();
+        for source in sources {
+            let source = self.wrap(source);
+            if self.contains(source) && distances.get(source).is_none() {
+                distances.set(source, Some(0));
+                queue.push_back(source);
+            }
+        }
+
+        while let Some(pos) = queue.pop_front() {
+            let distance = (*distances.get(pos)).unwrap_or_default();
+            for next in self.neighbours_4(pos) {
+                if distances.get(next).is_none() && passable(self.get(next)) {
+                    distances.set(next, Some(distance + 1));
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+
+    /// Whether every cell on the line between `from` and `to` is transparent. The end points
+    /// themselves are not checked, so an entity standing next to a wall can still be seen.
+    pub fn line_of_sight(
+        &self,
+        from: Vector2i,
+        to: Vector2i,
+        transparent: impl Fn(&T) -> bool,
+    ) -> bool {
+        bresenham_line(from, to)
+            .into_iter()
+            .filter(|pos| *pos != from && *pos != to)
+            .all(|pos| self.contains(pos) && transparent(self.get(pos)))
+    }
+}
+
+/// Returns the cells on the line from `from` to `to`, both included.
+pub fn bresenham_line(from: Vector2i, to: Vector2i) -> Vec<Vector2i> {
+    let dx = (to.x - from.x).abs();
+    let dy = -(to.y - from.y).abs();
+    let step_x = if from.x < to.x { 1 } else { -1 };
+    let step_y = if from.y < to.y { 1 } else { -1 };
+
+    let mut line = Vec::new();
+    let mut pos = from;
+    let mut error = dx + dy;
+    loop {
+        line.push(pos);
+        if pos == to {
+            return line;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            pos.x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            pos.y += step_y;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a grid where `#` is blocked and every other character is open.
+    fn parse(rows: &[&str]) -> Grid<bool> {
+        Grid::from_fn(rows[0].len(), rows.len(), |pos| {
+            rows[pos.y as usize].as_bytes()[pos.x as usize] != b'#'
+        })
+    }
+
+    fn sorted(mut cells: Vec<Vector2i>) -> Vec<Vector2i> {
+        cells.sort_by_key(|cell| (cell.y, cell.x));
+        cells
+    }
+
+    #[test]
+    fn neighbours_in_the_middle() {
+        let grid = parse(&["...", "...", "..."]);
+        let center = Vector2i::new(1, 1);
+        assert_eq!(grid.neighbours_4(center).count(), 4);
+        assert_eq!(grid.neighbours_8(center).count(), 8);
+    }
+
+    #[test]
+    fn neighbours_stop_at_edges() {
+        let grid = parse(&["...", "...", "..."]);
+        let corner = Vector2i::new(0, 0);
+        assert_eq!(
+            sorted(grid.neighbours_4(corner).collect()),
+            vec![Vector2i::new(1, 0), Vector2i::new(0, 1)]
+        );
+        assert_eq!(grid.neighbours_8(corner).count(), 3);
+        assert_eq!(grid.neighbours_4(Vector2i::new(2, 1)).count(), 3);
+        assert_eq!(grid.neighbours_8(Vector2i::new(1, 2)).count(), 5);
+    }
+
+    #[test]
+    fn flood_fill_stays_in_region() {
+        let grid = parse(&["..#..", "..#..", "#####", "....."]);
+        let region = grid.flood_fill(Vector2i::new(0, 0), |open| *open);
+        assert_eq!(region[0], Vector2i::new(0, 0));
+        assert_eq!(
+            sorted(region),
+            vec![
+                Vector2i::new(0, 0),
+                Vector2i::new(1, 0),
+                Vector2i::new(0, 1),
+                Vector2i::new(1, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn flood_fill_from_blocked_or_outside_is_empty() {
+        let grid = parse(&[".#", ".."]);
+        assert!(
+            grid.flood_fill(Vector2i::new(1, 0), |open| *open)
+                .is_empty()
+        );
+        assert!(
+            grid.flood_fill(Vector2i::new(-1, 0), |open| *open)
+                .is_empty()
+        );
+        assert!(
+            grid.flood_fill(Vector2i::new(0, 2), |open| *open)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn regions_are_split_by_walls() {
+        let grid = parse(&["..#..", "..#..", "#####", "....."]);
+        let regions = grid.regions(|open| *open);
+        let sizes: Vec<usize> = regions.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![4, 4, 5]);
+        assert_eq!(regions[1][0], Vector2i::new(3, 0));
+        assert_eq!(regions[2][0], Vector2i::new(0, 3));
+    }
+
+    #[test]
+    fn regions_of_blocked_grid_are_empty() {
+        let grid = parse(&["##", "##"]);
+        assert!(grid.regions(|open| *open).is_empty());
+    }
+
+    #[test]
+    fn distance_map_walks_around_walls() {
+        let grid = parse(&["...", "##.", "..."]);
+        let distances = grid.distance_map([Vector2i::new(0, 0)], |open| *open);
+        assert_eq!(*distances.get(Vector2i::new(0, 0)), Some(0));
+        assert_eq!(*distances.get(Vector2i::new(2, 0)), Some(2));
+        assert_eq!(*distances.get(Vector2i::new(0, 2)), Some(6));
+        assert_eq!(*distances.get(Vector2i::new(0, 1)), None);
+    }
+
+    #[test]
+    fn distance_map_uses_closest_source() {
+        let grid = parse(&["....."]);
+        let sources = [
+            Vector2i::new(0, 0),
+            Vector2i::new(4, 0),
+            Vector2i::new(9, 0),
+        ];
+        let distances = grid.distance_map(sources, |open| *open);
+        let row: Vec<Option<u32>> = distances.row(0).to_vec();
+        assert_eq!(row, vec![Some(0), Some(1), Some(2), Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn distance_map_leaves_unreachable_cells_empty() {
+        let grid = parse(&[".#."]);
+        let distances = grid.distance_map([Vector2i::new(0, 0)], |open| *open);
+        assert_eq!(*distances.get(Vector2i::new(2, 0)), None);
+    }
+
+    #[test]
+    fn line_of_sight_is_blocked_by_walls() {
+        let grid = parse(&[".....", "..#..", "....."]);
+        assert!(!grid.line_of_sight(Vector2i::new(0, 1), Vector2i::new(4, 1), |open| *open));
+        assert!(grid.line_of_sight(Vector2i::new(0, 0), Vector2i::new(4, 0), |open| *open));
+    }
+
+    #[test]
+    fn line_of_sight_ignores_end_points() {
+        let grid = parse(&["#.#"]);
+        assert!(grid.line_of_sight(Vector2i::new(0, 0), Vector2i::new(2, 0), |open| *open));
+    }
+
+    #[test]
+    fn line_of_sight_leaving_the_grid_is_blocked() {
+        let grid = parse(&["...", "..."]);
+        assert!(!grid.line_of_sight(Vector2i::new(0, 0), Vector2i::new(5, 0), |open| *open));
+    }
+
+    #[test]
+    fn toroidal_neighbours_wrap_onto_the_grid() {
+        let grid = parse(&["...", "...", "..."]).with_toroidal(true);
+        let corner = sorted(grid.neighbours_8(Vector2i::new(0, 0)).collect());
+        assert_eq!(corner.len(), 8);
+        assert!(corner.iter().all(|cell| grid.wrap(*cell) == *cell));
+        assert!(corner.contains(&Vector2i::new(2, 2)));
+        assert!(corner.contains(&Vector2i::new(0, 2)));
+    }
+
+    #[test]
+    fn toroidal_searches_stay_on_the_grid() {
+        let grid = parse(&["..#..", "###.#", "..#.."]).with_toroidal(true);
+        let in_grid = |cell: &Vector2i| grid.wrap(*cell) == *cell;
+
+        let region = grid.flood_fill(Vector2i::new(-1, 0), |open| *open);
+        assert_eq!(region[0], Vector2i::new(4, 0));
+        assert!(region.iter().all(in_grid));
+        // The left and right halves touch across the edges
+        assert!(region.contains(&Vector2i::new(0, 0)));
+        assert!(region.contains(&Vector2i::new(1, 2)));
+        let mut unique = sorted(region.clone());
+        unique.dedup();
+        assert_eq!(unique.len(), region.len());
+
+        let regions = grid.regions(|open| *open);
+        assert_eq!(regions.len(), 1);
+        assert!(regions[0].iter().all(in_grid));
+
+        let distances = grid.distance_map([Vector2i::new(0, 0)], |open| *open);
+        assert_eq!(*distances.get(Vector2i::new(4, 0)), Some(1));
+        assert_eq!(*distances.get(Vector2i::new(0, 2)), Some(1));
+    }
+
+    #[test]
+    fn bresenham_line_includes_both_ends() {
+        let line = bresenham_line(Vector2i::new(0, 0), Vector2i::new(3, 1));
+        assert_eq!(line.first(), Some(&Vector2i::new(0, 0)));
+        assert_eq!(line.last(), Some(&Vector2i::new(3, 1)));
+        assert_eq!(line.len(), 4);
+    }
+
+    #[test]
+    fn bresenham_line_steps_one_cell_at_a_time() {
+        for to in [
+            Vector2i::new(5, -3),
+            Vector2i::new(-4, 7),
+            Vector2i::new(-6, -6),
+            Vector2i::new(0, 4),
+        ] {
+            let line = bresenham_line(Vector2i::new(1, 1), to);
+            for pair in line.windows(2) {
+                let step = pair[1] - pair[0];
+                assert!(step.x.abs() <= 1 && step.y.abs() <= 1 && step != Vector2i::ZERO);
+            }
+            assert_eq!(line.last(), Some(&to));
+        }
+    }
+
+    #[test]
+    fn bresenham_line_of_one_cell() {
+        let pos = Vector2i::new(2, 3);
+        assert_eq!(bresenham_line(pos, pos), vec![pos]);
+    }
+}
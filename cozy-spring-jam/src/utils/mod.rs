@@ -1,2 +1,8 @@
 mod grid;
+mod grid_search;
+mod noise;
+mod seed;
 pub use grid::*;
+pub use grid_search::*;
+pub use noise::*;
+pub use seed::*;
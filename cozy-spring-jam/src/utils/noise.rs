@@ -0,0 +1,244 @@
+use std::f32::consts::{FRAC_1_SQRT_2, SQRT_2};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseType {
+    Value,
+    Perlin,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FractalType {
+    None,
+    Fbm,
+    Ridged,
+}
+
+/// Deterministic 2D noise in the spirit of Godot's `FastNoiseLite`, usable without an engine.
+#[derive(Debug, Clone)]
+pub struct Noise {
+    noise_type: NoiseType,
+    seed: i32,
+    frequency: f32,
+    fractal_type: FractalType,
+    fractal_octaves: i32,
+    fractal_lacunarity: f32,
+    fractal_gain: f32,
+}
+
+impl Noise {
+    const PRIME_X: i32 = 501125321;
+    const PRIME_Y: i32 = 1136930381;
+
+    pub fn new(noise_type: NoiseType, seed: i32) -> Self {
+        Self {
+            noise_type,
+            seed,
+            frequency: 0.01,
+            fractal_type: FractalType::Fbm,
+            fractal_octaves: 5,
+            fractal_lacunarity: 2.0,
+            fractal_gain: 0.5,
+        }
+    }
+
+    pub fn with_frequency(mut self, frequency: f32) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    pub fn with_fractal_type(mut self, fractal_type: FractalType) -> Self {
+        self.fractal_type = fractal_type;
+        self
+    }
+
+    pub fn with_fractal_octaves(mut self, octaves: i32) -> Self {
+        self.fractal_octaves = octaves;
+        self
+    }
+
+    pub fn with_fractal_gain(mut self, gain: f32) -> Self {
+        self.fractal_gain = gain;
+        self
+    }
+
+    /// Returns the noise value at `(x, y)`, roughly in the range `[-1, 1]`.
+    pub fn get_noise_2d(&self, x: f32, y: f32) -> f32 {
+        let x = x * self.frequency;
+        let y = y * self.frequency;
+
+        match self.fractal_type {
+            FractalType::None => self.single(self.seed, x, y),
+            FractalType::Fbm => self.fractal_fbm(x, y),
+            FractalType::Ridged => self.fractal_ridged(x, y),
+        }
+    }
+
+    fn fractal_bounding(&self) -> f32 {
+        let mut amp = self.fractal_gain;
+        let mut amp_fractal = 1.0;
+        for _ in 1..self.fractal_octaves {
+            amp_fractal += amp;
+            amp *= self.fractal_gain;
+        }
+        1.0 / amp_fractal
+    }
+
+    fn fractal_fbm(&self, mut x: f32, mut y: f32) -> f32 {
+        let mut seed = self.seed;
+        let mut sum = 0.0;
+        let mut amp = self.fractal_bounding();
+
+        for _ in 0..self.fractal_octaves {
+            sum += self.single(seed, x, y) * amp;
+            seed = seed.wrapping_add(1);
+            x *= self.fractal_lacunarity;
+            y *= self.fractal_lacunarity;
+            amp *= self.fractal_gain;
+        }
+        sum
+    }
+
+    fn fractal_ridged(&self, mut x: f32, mut y: f32) -> f32 {
+        let mut seed = self.seed;
+        let mut sum = 0.0;
+        let mut amp = self.fractal_bounding();
+
+        for _ in 0..self.fractal_octaves {
+            let noise = self.single(seed, x, y).abs();
+            sum += (noise * -2.0 + 1.0) * amp;
+            seed = seed.wrapping_add(1);
+            x *= self.fractal_lacunarity;
+            y *= self.fractal_lacunarity;
+            amp *= self.fractal_gain;
+        }
+        sum
+    }
+
+    fn single(&self, seed: i32, x: f32, y: f32) -> f32 {
+        match self.noise_type {
+            NoiseType::Value => Self::single_value(seed, x, y),
+            NoiseType::Perlin => Self::single_perlin(seed, x, y),
+        }
+    }
+
+    fn single_value(seed: i32, x: f32, y: f32) -> f32 {
+        let x0 = x.floor() as i32;
+        let y0 = y.floor() as i32;
+
+        let xs = interp_hermite(x - x0 as f32);
+        let ys = interp_hermite(y - y0 as f32);
+
+        let x0 = x0.wrapping_mul(Self::PRIME_X);
+        let y0 = y0.wrapping_mul(Self::PRIME_Y);
+        let x1 = x0.wrapping_add(Self::PRIME_X);
+        let y1 = y0.wrapping_add(Self::PRIME_Y);
+
+        let xf0 = lerp(value_coord(seed, x0, y0), value_coord(seed, x1, y0), xs);
+        let xf1 = lerp(value_coord(seed, x0, y1), value_coord(seed, x1, y1), xs);
+        lerp(xf0, xf1, ys)
+    }
+
+    fn single_perlin(seed: i32, x: f32, y: f32) -> f32 {
+        let x0 = x.floor() as i32;
+        let y0 = y.floor() as i32;
+
+        let xd0 = x - x0 as f32;
+        let yd0 = y - y0 as f32;
+        let xd1 = xd0 - 1.0;
+        let yd1 = yd0 - 1.0;
+
+        let xs = interp_quintic(xd0);
+        let ys = interp_quintic(yd0);
+
+        let x0 = x0.wrapping_mul(Self::PRIME_X);
+        let y0 = y0.wrapping_mul(Self::PRIME_Y);
+        let x1 = x0.wrapping_add(Self::PRIME_X);
+        let y1 = y0.wrapping_add(Self::PRIME_Y);
+
+        let xf0 = lerp(
+            grad_coord(seed, x0, y0, xd0, yd0),
+            grad_coord(seed, x1, y0, xd1, yd0),
+            xs,
+        );
+        let xf1 = lerp(
+            grad_coord(seed, x0, y1, xd0, yd1),
+            grad_coord(seed, x1, y1, xd1, yd1),
+            xs,
+        );
+        lerp(xf0, xf1, ys) * SQRT_2
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + t * (b - a)
+}
+
+fn interp_hermite(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn interp_quintic(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn hash(seed: i32, x_primed: i32, y_primed: i32) -> i32 {
+    (seed ^ x_primed ^ y_primed).wrapping_mul(0x27d4eb2d)
+}
+
+fn value_coord(seed: i32, x_primed: i32, y_primed: i32) -> f32 {
+    let mut hash = hash(seed, x_primed, y_primed);
+    hash = hash.wrapping_mul(hash);
+    hash ^= hash << 19;
+    hash as f32 * (1.0 / 2147483648.0)
+}
+
+fn grad_coord(seed: i32, x_primed: i32, y_primed: i32, xd: f32, yd: f32) -> f32 {
+    const D: f32 = FRAC_1_SQRT_2;
+    const GRADIENTS: [(f32, f32); 8] = [
+        (1.0, 0.0),
+        (-1.0, 0.0),
+        (0.0, 1.0),
+        (0.0, -1.0),
+        (D, D),
+        (-D, D),
+        (D, -D),
+        (-D, -D),
+    ];
+
+    let hash = hash(seed, x_primed, y_primed);
+    let (gx, gy) = GRADIENTS[((hash >> 15) & 7) as usize];
+    xd * gx + yd * gy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINTS: [(f32, f32); 3] = [(0.0, 0.0), (3.5, -2.25), (17.0, 40.0)];
+
+    fn sample(noise: &Noise) -> Vec<f32> {
+        POINTS
+            .iter()
+            .map(|&(x, y)| noise.get_noise_2d(x, y))
+            .collect()
+    }
+
+    #[test]
+    fn perlin_matches_snapshot() {
+        let noise = Noise::new(NoiseType::Perlin, 5).with_frequency(0.1);
+        assert_eq!(sample(&noise), [0.0, -0.1443253, 0.18884288]);
+    }
+
+    #[test]
+    fn value_matches_snapshot() {
+        let noise = Noise::new(NoiseType::Value, 11).with_frequency(0.1);
+        assert_eq!(sample(&noise), [-0.682422, -0.13326766, 0.15297563]);
+    }
+
+    #[test]
+    fn seeds_give_different_noise() {
+        let a = Noise::new(NoiseType::Perlin, 1).with_frequency(0.1);
+        let b = Noise::new(NoiseType::Perlin, 2).with_frequency(0.1);
+        assert_ne!(sample(&a), sample(&b));
+    }
+}
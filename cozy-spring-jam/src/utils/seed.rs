@@ -0,0 +1,16 @@
+use godot::builtin::Vector2i;
+
+/// Mixes `value` into `seed` using a SplitMix64 step, so that neighbouring inputs end up with
+/// unrelated seeds.
+pub fn mix_seed(seed: u64, value: u64) -> u64 {
+    let mut z = seed.wrapping_add(value).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Seed of the room at `coord`, which only depends on the run seed and the coordinate itself.
+pub fn room_seed(run_seed: u64, coord: Vector2i) -> u64 {
+    let seed = mix_seed(run_seed, coord.x as u32 as u64);
+    mix_seed(seed, coord.y as u32 as u64)
+}
@@ -0,0 +1,244 @@
+mod map;
+pub mod minimap;
+
+use crate::room::Room;
+use crate::room::generation::{FloorTile, RoomLayout, RoomType};
+use crate::room::state::RoomState;
+use crate::world::map::WorldMap;
+use godot::{classes::RandomNumberGenerator, prelude::*};
+use std::collections::{HashMap, HashSet};
+
+/// A room shown on the map.
+pub struct DiscoveredRoom {
+    pub coord: Vector2i,
+    pub room_type: RoomType,
+    pub layout: RoomLayout,
+    /// Whether the player has been inside the room, rather than only seen its door.
+    pub visited: bool,
+}
+
+#[derive(GodotClass)]
+#[class(base=Node2D)]
+pub struct World {
+    /// Seed of the run. `0` picks a random seed when the first room is generated.
+    #[export]
+    seed: i64,
+
+    /// Rooms further away from the player than this, in rooms, get unloaded.
+    #[export]
+    stream_distance: i32,
+
+    run_seed: Option<u64>,
+
+    map: Option<WorldMap>,
+
+    rooms: HashMap<Vector2i, Gd<Room>>,
+
+    room_size: Vector2,
+
+    room_states: HashMap<Vector2i, RoomState>,
+
+    visited: HashSet<Vector2i>,
+
+    current_room: Option<Vector2i>,
+
+    base: Base<Node2D>,
+}
+
+#[godot_api]
+impl INode2D for World {
+    fn init(base: Base<Node2D>) -> Self {
+        Self {
+            seed: 0,
+            stream_distance: 2,
+            run_seed: None,
+            map: None,
+            rooms: HashMap::new(),
+            room_size: Vector2::ZERO,
+            room_states: HashMap::new(),
+            visited: HashSet::new(),
+            current_room: None,
+            base,
+        }
+    }
+}
+
+#[godot_api]
+impl World {
+    /// Returns the loaded room containing the global position `pos`.
+    #[func]
+    fn room_at_position(&self, pos: Vector2) -> Option<Gd<Room>> {
+        self.room_containing(pos).map(|(_, room)| room)
+    }
+}
+
+impl World {
+    pub fn for_node(node: &Node) -> Option<Gd<World>> {
+        let tree = node.get_tree()?;
+        let scene = tree.get_current_scene()?;
+        let result = scene.get_node_or_null("World").map(Gd::cast);
+        if result.is_none() {
+            godot_error!("No World found in this scene!");
+        }
+        result
+    }
+
+    pub fn run_seed(&mut self) -> u64 {
+        if let Some(run_seed) = self.run_seed {
+            return run_seed;
+        }
+
+        let run_seed = if self.seed == 0 {
+            let mut rng = RandomNumberGenerator::new_gd();
+            ((rng.randi() as u64) << 32) | rng.randi() as u64
+        } else {
+            self.seed as u64
+        };
+        self.seed = run_seed as i64;
+        self.run_seed = Some(run_seed);
+        godot_print!("Run seed: {}", self.seed);
+        run_seed
+    }
+
+    /// Returns the layout of the room at the grid coordinate `coord`, matching the exits of all
+    /// neighbouring rooms.
+    pub fn room_layout(&mut self, coord: Vector2i) -> RoomLayout {
+        let run_seed = self.run_seed();
+        self.map
+            .get_or_insert_with(|| WorldMap::new(run_seed))
+            .layout(coord)
+    }
+
+    pub fn room_type(&mut self, coord: Vector2i) -> RoomType {
+        let run_seed = self.run_seed();
+        self.map
+            .get_or_insert_with(|| WorldMap::new(run_seed))
+            .room_type(coord)
+    }
+
+    pub fn register_room(&mut self, coord: Vector2i, room: Gd<Room>, room_size: Vector2) {
+        if self.room_size == Vector2::ZERO {
+            self.room_size = room_size;
+        }
+        if self.rooms.insert(coord, room).is_some() {
+            godot_error!("Duplicated room at {:?}!", coord);
+        }
+    }
+
+    pub fn has_room(&self, coord: Vector2i) -> bool {
+        self.rooms.contains_key(&coord)
+    }
+
+    pub fn room_at(&self, coord: Vector2i) -> Option<Gd<Room>> {
+        self.rooms.get(&coord).cloned()
+    }
+
+    pub fn rooms(&self) -> impl Iterator<Item = (Vector2i, Gd<Room>)> + '_ {
+        self.rooms
+            .iter()
+            .map(|(coord, room)| (*coord, room.clone()))
+    }
+
+    /// Returns the grid coordinate of the room slot containing the global position `pos`.
+    pub fn coord_at_position(&self, pos: Vector2) -> Option<Vector2i> {
+        if self.room_size.x <= 0.0 || self.room_size.y <= 0.0 {
+            return None;
+        }
+        let local = self.base().to_local(pos) / self.room_size;
+        Some(Vector2i::new(
+            local.x.floor() as i32,
+            local.y.floor() as i32,
+        ))
+    }
+
+    pub fn room_containing(&self, pos: Vector2) -> Option<(Vector2i, Gd<Room>)> {
+        let coord = self.coord_at_position(pos)?;
+        self.room_at(coord).map(|room| (coord, room))
+    }
+
+    /// Returns the floor tile under the global position `pos`, if a room is loaded there.
+    pub fn floor_at(&self, pos: Vector2) -> FloorTile {
+        self.room_containing(pos)
+            .map_or(FloorTile::None, |(_, room)| room.bind().floor_at(pos))
+    }
+
+    /// Saves and frees every loaded room further than `stream_distance` away from `center`.
+    pub fn stream_rooms(&mut self, center: Vector2i) {
+        let far_rooms: Vec<Vector2i> = self
+            .rooms
+            .keys()
+            .filter(|coord| {
+                let dist = (**coord - center).abs();
+                dist.x.max(dist.y) > self.stream_distance
+            })
+            .copied()
+            .collect();
+
+        for coord in far_rooms {
+            let Some(mut room) = self.rooms.remove(&coord) else {
+                continue;
+            };
+            let state = room.bind().save_state();
+            self.room_states.insert(coord, state);
+            room.queue_free();
+            godot_print!("Unloaded room at {:?}", coord);
+        }
+    }
+
+    /// Marks the room at `coord` as the one the player is in.
+    pub fn enter_room(&mut self, coord: Vector2i) {
+        self.current_room = Some(coord);
+        self.visited.insert(coord);
+    }
+
+    pub fn current_room(&self) -> Option<Vector2i> {
+        self.current_room
+    }
+
+    /// Returns every visited room and the rooms behind their exits.
+    pub fn discovered_rooms(&self) -> Vec<DiscoveredRoom> {
+        let Some(map) = &self.map else {
+            return Vec::new();
+        };
+        let mut discovered: HashMap<Vector2i, bool> = HashMap::new();
+        for coord in &self.visited {
+            discovered.insert(*coord, true);
+            let Some(layout) = map.get(*coord) else {
+                continue;
+            };
+            for dir in layout.exits() {
+                discovered.entry(*coord + dir.offset()).or_insert(false);
+            }
+        }
+
+        discovered
+            .into_iter()
+            .filter_map(|(coord, visited)| {
+                Some(DiscoveredRoom {
+                    coord,
+                    room_type: map.room_type(coord),
+                    layout: map.get(coord)?.clone(),
+                    visited,
+                })
+            })
+            .collect()
+    }
+
+    /// Takes the saved state of an unloaded room, if there is one.
+    pub fn take_room_state(&mut self, coord: Vector2i) -> Option<RoomState> {
+        self.room_states.remove(&coord)
+    }
+
+    pub fn run_seed_for(node: &Node) -> u64 {
+        match Self::for_node(node) {
+            Some(mut world) => world.bind_mut().run_seed(),
+            None => 0,
+        }
+    }
+}
+
+pub fn seeded_rng(seed: u64) -> Gd<RandomNumberGenerator> {
+    let mut rng = RandomNumberGenerator::new_gd();
+    rng.set_seed(seed);
+    rng
+}
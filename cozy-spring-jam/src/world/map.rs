@@ -0,0 +1,190 @@
+use crate::room::generation::{Direction, RoomLayout, RoomType};
+use crate::utils::{mix_seed, room_seed};
+use godot::builtin::Vector2i;
+use std::collections::HashMap;
+
+/// Layouts of all rooms of a run, keyed by their grid coordinate.
+///
+/// Exits are decided per shared edge rather than per room, so both rooms next to an edge always
+/// agree on whether there is a door between them.
+#[derive(Debug, Clone)]
+pub struct WorldMap {
+    run_seed: u64,
+    layouts: HashMap<Vector2i, RoomLayout>,
+}
+
+impl WorldMap {
+    const EDGE_SEED_STREAM: u64 = 16;
+    const ROOM_TYPE_SEED_STREAM: u64 = 18;
+    const BOSS_MIN_DISTANCE: i32 = 4;
+
+    pub fn new(run_seed: u64) -> Self {
+        Self {
+            run_seed,
+            layouts: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, coord: Vector2i) -> Option<&RoomLayout> {
+        self.layouts.get(&coord)
+    }
+
+    /// Returns the layout of the room at `coord`, deciding it on first access.
+    pub fn layout(&mut self, coord: Vector2i) -> RoomLayout {
+        if let Some(layout) = self.layouts.get(&coord) {
+            return layout.clone();
+        }
+
+        let mut layout = RoomLayout::default();
+        for dir in Direction::ALL {
+            let open = match self.get(coord + dir.offset()) {
+                Some(neighbour) => neighbour.has_exit(dir.opposite()),
+                None => self.is_edge_open(coord, *dir),
+            };
+            layout.set_exit(*dir, open);
+        }
+        self.layouts.insert(coord, layout.clone());
+        layout
+    }
+
+    /// Returns the type of the room at `coord`, which only depends on the run seed.
+    pub fn room_type(&self, coord: Vector2i) -> RoomType {
+        if coord == Vector2i::ZERO {
+            return RoomType::Start;
+        }
+
+        let roll = self.room_type_roll(coord);
+        // Two boss rooms next to each other would close the edge between them from both sides,
+        // which could leave one without any entrance
+        let boss_neighbour = Direction::ALL
+            .iter()
+            .any(|dir| self.rolls_boss(coord + dir.offset()));
+        if self.rolls_boss(coord) && !boss_neighbour {
+            RoomType::Boss
+        } else if roll < 13 {
+            RoomType::Treasure
+        } else if roll < 18 {
+            RoomType::Shop
+        } else if roll < 25 {
+            RoomType::Safe
+        } else {
+            RoomType::Normal
+        }
+    }
+
+    fn room_type_roll(&self, coord: Vector2i) -> u64 {
+        mix_seed(room_seed(self.run_seed, coord), Self::ROOM_TYPE_SEED_STREAM) % 100
+    }
+
+    fn rolls_boss(&self, coord: Vector2i) -> bool {
+        let distance = coord.x.abs() + coord.y.abs();
+        self.room_type_roll(coord) < 5 && distance >= Self::BOSS_MIN_DISTANCE
+    }
+
+    /// The only door of a boss room, which faces towards the start room.
+    fn boss_entrance(coord: Vector2i) -> Direction {
+        if coord.x.abs() >= coord.y.abs() {
+            if coord.x > 0 {
+                Direction::Left
+            } else {
+                Direction::Right
+            }
+        } else if coord.y > 0 {
+            Direction::Top
+        } else {
+            Direction::Bottom
+        }
+    }
+
+    /// Whether there is a door on the edge in direction `dir` of the room at `coord`.
+    ///
+    /// The start room has doors on all sides and boss rooms only have their entrance. All other
+    /// edges are rolled from the seed of the room to their left or top, so both rooms sharing an
+    /// edge roll the same value.
+    fn is_edge_open(&self, coord: Vector2i, dir: Direction) -> bool {
+        let sides = [(coord, dir), (coord + dir.offset(), dir.opposite())];
+        let mut forced_open = false;
+        for (side, side_dir) in sides {
+            match self.room_type(side) {
+                RoomType::Start => forced_open = true,
+                RoomType::Boss if Self::boss_entrance(side) != side_dir => return false,
+                RoomType::Boss => forced_open = true,
+                _ => (),
+            }
+        }
+        if forced_open {
+            return true;
+        }
+
+        let (owner, axis) = match dir {
+            Direction::Right | Direction::Bottom => (coord, dir),
+            Direction::Left | Direction::Top => (coord + dir.offset(), dir.opposite()),
+        };
+        let seed = room_seed(self.run_seed, owner);
+        let stream = match axis {
+            Direction::Bottom => Self::EDGE_SEED_STREAM + 1,
+            _ => Self::EDGE_SEED_STREAM,
+        };
+        mix_seed(seed, stream) % 2 == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boss_rooms_are_never_neighbours() {
+        for run_seed in 0..20 {
+            let map = WorldMap::new(run_seed);
+            for x in -15..=15 {
+                for y in -15..=15 {
+                    let coord = Vector2i::new(x, y);
+                    if map.room_type(coord) != RoomType::Boss {
+                        continue;
+                    }
+                    for dir in Direction::ALL {
+                        assert_ne!(map.room_type(coord + dir.offset()), RoomType::Boss);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn boss_entrance_is_open() {
+        for run_seed in 0..20 {
+            let mut map = WorldMap::new(run_seed);
+            let mut bosses = 0;
+            for x in -15..=15 {
+                for y in -15..=15 {
+                    let coord = Vector2i::new(x, y);
+                    if map.room_type(coord) != RoomType::Boss {
+                        continue;
+                    }
+                    bosses += 1;
+                    let layout = map.layout(coord);
+                    let entrance = WorldMap::boss_entrance(coord);
+                    assert!(layout.has_exit(entrance), "boss room {coord:?} is closed");
+                    assert_eq!(layout.exits().count(), 1);
+                }
+            }
+            assert!(bosses > 0);
+        }
+    }
+
+    #[test]
+    fn neighbours_agree_on_doors() {
+        let mut map = WorldMap::new(7);
+        for x in -6..=6 {
+            for y in -6..=6 {
+                let coord = Vector2i::new(x, y);
+                let layout = map.layout(coord);
+                for dir in Direction::ALL {
+                    let neighbour = map.layout(coord + dir.offset());
+                    assert_eq!(layout.has_exit(*dir), neighbour.has_exit(dir.opposite()));
+                }
+            }
+        }
+    }
+}
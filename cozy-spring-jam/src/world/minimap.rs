@@ -0,0 +1,194 @@
+use crate::room::generation::RoomType;
+use crate::world::{DiscoveredRoom, World};
+use godot::classes::{Control, IControl, InputEvent};
+use godot::prelude::*;
+
+/// Draws the rooms the player discovered, centered on the current room. The `toggle_map` action
+/// switches between a small corner map and a full-screen map of everything discovered.
+#[derive(GodotClass)]
+#[class(base=Control)]
+pub struct Minimap {
+    /// Size of one room on the corner map, in pixels.
+    #[export]
+    room_size: Vector2,
+
+    /// Size of one room on the full-screen map, in pixels.
+    #[export]
+    full_screen_room_size: Vector2,
+
+    /// Space between two rooms, as a fraction of the room size.
+    #[export]
+    room_gap: f32,
+
+    #[export]
+    full_screen: bool,
+
+    #[export]
+    background_color: Color,
+
+    #[export]
+    visited_color: Color,
+
+    #[export]
+    unvisited_color: Color,
+
+    #[export]
+    current_color: Color,
+
+    #[export]
+    exit_color: Color,
+
+    #[export]
+    start_color: Color,
+
+    #[export]
+    boss_color: Color,
+
+    #[export]
+    treasure_color: Color,
+
+    #[export]
+    shop_color: Color,
+
+    #[export]
+    safe_color: Color,
+
+    base: Base<Control>,
+}
+
+#[godot_api]
+impl IControl for Minimap {
+    fn init(base: Base<Control>) -> Self {
+        Self {
+            room_size: Vector2::new(18.0, 11.0),
+            full_screen_room_size: Vector2::new(36.0, 22.0),
+            room_gap: 0.3,
+            full_screen: false,
+            background_color: Color::from_rgba(0.0, 0.0, 0.0, 0.5),
+            visited_color: Color::from_rgb(0.55, 0.7, 0.45),
+            unvisited_color: Color::from_rgba(0.55, 0.7, 0.45, 0.35),
+            current_color: Color::WHITE,
+            exit_color: Color::from_rgb(0.85, 0.8, 0.6),
+            start_color: Color::from_rgb(0.4, 0.6, 1.0),
+            boss_color: Color::from_rgb(0.9, 0.2, 0.2),
+            treasure_color: Color::from_rgb(1.0, 0.85, 0.2),
+            shop_color: Color::from_rgb(0.3, 0.85, 0.85),
+            safe_color: Color::from_rgb(1.0, 0.6, 0.8),
+            base,
+        }
+    }
+
+    fn process(&mut self, _delta: f64) {
+        self.base_mut().queue_redraw();
+    }
+
+    fn unhandled_input(&mut self, event: Gd<InputEvent>) {
+        if event.is_action_pressed("toggle_map") {
+            self.full_screen = !self.full_screen;
+            if let Some(mut viewport) = self.base().get_viewport() {
+                viewport.set_input_as_handled();
+            }
+        }
+    }
+
+    fn draw(&mut self) {
+        let Some(world) = World::for_node(self.base().upcast_ref()) else {
+            return;
+        };
+        let (rooms, current) = {
+            let world = world.bind();
+            (world.discovered_rooms(), world.current_room())
+        };
+        let Some(current) = current else {
+            return;
+        };
+
+        let area = self.map_area();
+        let room_size = if self.full_screen {
+            self.full_screen_room_size
+        } else {
+            self.room_size
+        };
+        let spacing = room_size * (1.0 + self.room_gap);
+        let center = area.center();
+        let background_color = self.background_color;
+        self.base_mut().draw_rect(area, background_color);
+
+        for room in &rooms {
+            let room_center = center + (room.coord - current).cast_float() * spacing;
+            let rect = Rect2::new(room_center - room_size / 2.0, room_size);
+            if !area.encloses(rect) {
+                continue;
+            }
+            self.draw_room(room, rect, spacing);
+            if room.coord == current {
+                let current_color = self.current_color;
+                self.base_mut()
+                    .draw_rect_ex(rect, current_color)
+                    .filled(false)
+                    .width(2.0)
+                    .done();
+            }
+        }
+    }
+}
+
+impl Minimap {
+    /// The rectangle the map is drawn in, in local coordinates. The full-screen map covers the
+    /// whole viewport.
+    fn map_area(&self) -> Rect2 {
+        if self.full_screen {
+            let viewport = self.base().get_viewport_rect();
+            Rect2::new(
+                viewport.position - self.base().get_global_position(),
+                viewport.size,
+            )
+        } else {
+            Rect2::new(Vector2::ZERO, self.base().get_size())
+        }
+    }
+
+    fn draw_room(&mut self, room: &DiscoveredRoom, rect: Rect2, spacing: Vector2) {
+        let color = if room.visited {
+            self.visited_color
+        } else {
+            self.unvisited_color
+        };
+        self.base_mut().draw_rect(rect, color);
+        if !room.visited {
+            return;
+        }
+
+        let center = rect.center();
+        let exit_color = self.exit_color;
+        for dir in room.layout.exits() {
+            let offset = dir.offset().cast_float();
+            let (from, to) = if dir.is_vertical() {
+                (rect.size.y / 2.0, spacing.y / 2.0)
+            } else {
+                (rect.size.x / 2.0, spacing.x / 2.0)
+            };
+            self.base_mut()
+                .draw_line_ex(center + offset * from, center + offset * to, exit_color)
+                .width(2.0)
+                .done();
+        }
+
+        if let Some(marker_color) = self.room_type_color(room.room_type) {
+            let radius = rect.size.x.min(rect.size.y) / 4.0;
+            self.base_mut().draw_circle(center, radius, marker_color);
+        }
+    }
+
+    /// The color of the marker drawn on rooms of the given type, if they have one.
+    fn room_type_color(&self, room_type: RoomType) -> Option<Color> {
+        match room_type {
+            RoomType::Start => Some(self.start_color),
+            RoomType::Boss => Some(self.boss_color),
+            RoomType::Treasure => Some(self.treasure_color),
+            RoomType::Shop => Some(self.shop_color),
+            RoomType::Safe => Some(self.safe_color),
+            RoomType::Normal => None,
+        }
+    }
+}
@@ -11,6 +11,9 @@ func _ready() -> void:
 	LimboConsole.register_command(list_npcs)
 	LimboConsole.register_command(damage_npc)
 	LimboConsole.register_command(damage_player)
+	LimboConsole.register_command(dump_room)
+	LimboConsole.register_command(save_room)
+	LimboConsole.register_command(load_room)
 
 
 func spawn_enemy(position_x: int, position_y: int) -> void:
@@ -27,11 +30,11 @@ func spawn_enemy(position_x: int, position_y: int) -> void:
 
 func set_health(new_health: int) -> void:
 	var player_node: Player = get_tree().get_current_scene().find_child("PlayerScene")
-	player_node.health = new_health
+	player_node.change_health(new_health)
 	
 func set_max_health(new_max_health: int) -> void:
 	var player_node: Player = get_tree().get_current_scene().find_child("PlayerScene")
-	player_node.max_health = new_max_health
+	player_node.set_max_health(new_max_health)
 
 func list_npcs() -> void:
 	var npc_list = get_tree().get_nodes_in_group("enemy")
@@ -45,3 +48,19 @@ func damage_npc(id: int, amount: int) -> void:
 func damage_player(amount: int) -> void:
 	var player_node: Player = get_tree().get_current_scene().find_child("PlayerScene")
 	player_node.damage_player(amount)
+
+func _current_room() -> Room:
+	var player_node: Player = get_tree().get_current_scene().find_child("PlayerScene")
+	var world: World = get_tree().get_current_scene().get_node("World")
+	return world.room_at_position(player_node.global_position)
+
+func dump_room() -> void:
+	LimboConsole.info(_current_room().dump_map())
+
+func save_room(path: String) -> void:
+	if _current_room().save_map(path):
+		LimboConsole.info("Saved room to " + path)
+
+func load_room(path: String) -> void:
+	if _current_room().load_map(path):
+		LimboConsole.info("Loaded room from " + path)